@@ -0,0 +1,272 @@
+use crate::error::{KError, Span};
+use crate::parse::{Function, Node, Operand};
+use crate::token::{Control, Token};
+use crate::value::Verb;
+use std::rc::Rc;
+
+/// An instruction of the stack machine run by `execute`. Verbs and adverbs
+/// are referred to by their index in the dispatch tables. Instructions that
+/// can fail carry the span of source their errors are reported at.
+#[derive(Clone, Debug)]
+pub(crate) enum Op {
+    /// Push the value of a noun token: a literal, symbols or a name.
+    Noun(Token, Span),
+    /// Pop `n` items and push them as a list.
+    List(usize),
+    /// Pop an argument and push the monadic verb applied to it.
+    Monad(usize, Span),
+    /// Pop the left and then the right argument and push the dyadic verb
+    /// applied to them.
+    Dyad(usize, Span),
+    /// Pop the operand, unless `verb` is the index of a built-in verb, and
+    /// the left argument if `dyadic`, then the right argument, and push the
+    /// derived verb applied to them.
+    Adverb {
+        adverb: usize,
+        verb: Option<usize>,
+        dyadic: bool,
+        span: Span,
+    },
+    /// Assign the value on top of the stack, leaving it there.
+    Assign(Rc<str>),
+    /// Assign the value on top of the stack to a global, leaving it there.
+    AssignGlobal(Rc<str>),
+    /// Pop a path of `n` indices and then a value, amend the variable with
+    /// it and push the value.
+    Amend(Rc<str>, usize, Span),
+    /// Pop a function and then `n` arguments, and push the result of calling
+    /// or indexing it.
+    Apply(usize, Span),
+    /// Discard the value on top of the stack.
+    Pop,
+    /// Continue at the given instruction.
+    Jump(usize),
+    /// Pop a condition and continue at the given instruction if it is false.
+    /// A condition that is not a number or a char is a domain error.
+    JumpUnless(usize, Span),
+    /// Count down the loop counter on top of the stack, or pop it and
+    /// continue at the given instruction once it reaches zero.
+    Repeat(usize, Span),
+    /// Push a built-in verb as a value.
+    Verb(Verb),
+    /// Pop a lambda or verb and push it derived with an adverb: `f/`.
+    Derive(usize),
+    /// Pop the left argument of a built-in dyadic verb and push the verb
+    /// projected onto it: `2+`.
+    Project(usize),
+    /// Pop the outer and then the inner verb of a composition and push the
+    /// composition: `*|`.
+    Compose,
+    /// Raise an error found while compiling.
+    Error(KError),
+}
+
+/// Compile a syntax tree to instructions that leave its value on the stack.
+pub(crate) fn compile(node: &Node) -> Vec<Op> {
+    let mut code = Vec::new();
+    compile_node(&mut code, node);
+    code
+}
+
+fn compile_node(code: &mut Vec<Op>, node: &Node) {
+    let mut chain = Vec::new();
+    let mut node = node;
+    while let Some(right) = node.right_argument() {
+        chain.push(node);
+        node = right;
+    }
+    match node {
+        Node::Noun(token, span) => code.push(Op::Noun(token.clone(), *span)),
+        Node::List(items) => {
+            compile_items(code, items);
+            code.push(Op::List(items.len()));
+        }
+        Node::Apply(function, arguments, span) => {
+            compile_items(code, arguments);
+            compile_node(code, function);
+            code.push(Op::Apply(arguments.len(), *span));
+        }
+        Node::Control(control, span, items) => compile_control(code, *control, *span, items),
+        Node::Block(statements) => compile_statements(code, statements),
+        Node::Function(function) => compile_function(code, function),
+        _ => unreachable!("Node::right_argument covers the other nodes"),
+    }
+    for node in chain.into_iter().rev() {
+        compile_completion(code, node);
+    }
+}
+
+/// Compile the rest of a node that takes a right argument, once the code
+/// for that argument has been emitted.
+fn compile_completion(code: &mut Vec<Op>, node: &Node) {
+    match node {
+        Node::Monad(verb, span, _) => code.push(Op::Monad(*verb, *span)),
+        Node::Dyad(verb, span, left, _) => {
+            compile_node(code, left);
+            code.push(Op::Dyad(*verb, *span));
+        }
+        Node::Derived {
+            operand,
+            adverb,
+            span,
+            left,
+            ..
+        } => {
+            let verb = match operand {
+                Operand::Verb(verb) => Some(*verb),
+                operand => {
+                    compile_operand(code, operand);
+                    None
+                }
+            };
+            if let Some(left) = left {
+                compile_node(code, left);
+            }
+            code.push(Op::Adverb {
+                adverb: *adverb,
+                verb,
+                dyadic: left.is_some(),
+                span: *span,
+            });
+        }
+        Node::Assign {
+            name,
+            span,
+            global,
+            path,
+            ..
+        } => match path {
+            Some(path) => {
+                compile_items(code, path);
+                code.push(Op::Amend(name.clone(), path.len(), *span));
+            }
+            None if *global => code.push(Op::AssignGlobal(name.clone())),
+            None => code.push(Op::Assign(name.clone())),
+        },
+        Node::Apply(function, _, span) => {
+            compile_node(code, function);
+            code.push(Op::Apply(1, *span));
+        }
+        _ => unreachable!("only nodes with a right argument are completed"),
+    }
+}
+
+/// Compile the operand of an adverb to code that pushes the verb it stands
+/// for.
+fn compile_operand(code: &mut Vec<Op>, operand: &Operand) {
+    match operand {
+        Operand::Verb(verb) => code.push(Op::Verb(Verb::Builtin(*verb))),
+        Operand::Noun(noun) => compile_node(code, noun),
+        Operand::Derived(operand, adverb) => {
+            compile_operand(code, operand);
+            code.push(Op::Derive(*adverb));
+        }
+    }
+}
+
+/// Compile a verb written as a value. The verbs of a composition are built
+/// right to left, and any argument a projection fixes is compiled with them.
+fn compile_function(code: &mut Vec<Op>, function: &Function) {
+    match function {
+        Function::Verb(verb) => code.push(Op::Verb(Verb::Builtin(*verb))),
+        Function::Monadic(verb) => code.push(Op::Verb(Verb::Monadic(*verb))),
+        Function::Derived(operand, adverb) => {
+            compile_operand(code, operand);
+            code.push(Op::Derive(*adverb));
+        }
+        Function::Projection(verb, left) => {
+            compile_node(code, left);
+            code.push(Op::Project(*verb));
+        }
+        Function::Composition(functions) => {
+            let mut functions = functions.iter().rev();
+            let Some(inner) = functions.next() else {
+                code.push(Op::Error(KError::parse()));
+                return;
+            };
+            compile_function(code, inner);
+            for outer in functions {
+                compile_function(code, outer);
+                code.push(Op::Compose);
+            }
+        }
+    }
+}
+
+/// Compile list items or arguments so that they are evaluated right to left
+/// and the first one ends up on top of the stack.
+fn compile_items(code: &mut Vec<Op>, items: &[Node]) {
+    for item in items.iter().rev() {
+        compile_node(code, item);
+    }
+}
+
+/// Compile statements that leave the value of the last one on the stack, or
+/// the empty list if there are none.
+fn compile_statements(code: &mut Vec<Op>, statements: &[Node]) {
+    let Some((last, rest)) = statements.split_last() else {
+        code.push(Op::List(0));
+        return;
+    };
+    for statement in rest {
+        compile_node(code, statement);
+        code.push(Op::Pop);
+    }
+    compile_node(code, last);
+}
+
+/// Compile a control form with jumps, so that only the expressions that are
+/// needed are evaluated. Jump targets are patched once they are known.
+fn compile_control(code: &mut Vec<Op>, control: Control, span: Span, items: &[Node]) {
+    match (control, items) {
+        (Control::Cond, [_, _, ..]) => {
+            let mut exits = Vec::new();
+            let mut pairs = items.chunks_exact(2);
+            for pair in pairs.by_ref() {
+                compile_node(code, &pair[0]);
+                let test = code.len();
+                code.push(Op::JumpUnless(0, span));
+                compile_node(code, &pair[1]);
+                exits.push(code.len());
+                code.push(Op::Jump(0));
+                code[test] = Op::JumpUnless(code.len(), span);
+            }
+            match pairs.remainder() {
+                [otherwise] => compile_node(code, otherwise),
+                _ => code.push(Op::List(0)),
+            }
+            for exit in exits {
+                code[exit] = Op::Jump(code.len());
+            }
+        }
+        (Control::Do, [count, body @ ..]) => {
+            compile_node(code, count);
+            let start = code.len();
+            code.push(Op::Repeat(0, span));
+            compile_loop_body(code, body, start);
+            code[start] = Op::Repeat(code.len(), span);
+            code.push(Op::List(0));
+        }
+        (Control::While, [condition, body @ ..]) => {
+            let start = code.len();
+            compile_node(code, condition);
+            let test = code.len();
+            code.push(Op::JumpUnless(0, span));
+            compile_loop_body(code, body, start);
+            code[test] = Op::JumpUnless(code.len(), span);
+            code.push(Op::List(0));
+        }
+        // Too few expressions, as in `$[c]` or `do[]`.
+        _ => code.push(Op::Error(KError::rank().with_span(span))),
+    }
+}
+
+/// Compile the statements of a loop body, discarding their values, and jump
+/// back to `start`.
+fn compile_loop_body(code: &mut Vec<Op>, body: &[Node], start: usize) {
+    for statement in body {
+        compile_node(code, statement);
+        code.push(Op::Pop);
+    }
+    code.push(Op::Jump(start));
+}
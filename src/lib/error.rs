@@ -0,0 +1,148 @@
+use std::fmt::Display;
+
+/// A byte range in the k/simple source line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Where an error was raised: the k verb or adverb, such as `+` or `/`, if
+/// one raised it and, when known, the span of source that triggered it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Origin {
+    pub verb: Option<&'static str>,
+    pub span: Option<Span>,
+}
+
+impl Origin {
+    fn new() -> Self {
+        Self {
+            verb: None,
+            span: None,
+        }
+    }
+}
+
+/// An error raised while evaluating k/simple code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KError {
+    /// An operand has the wrong shape, e.g. an atom where a vector is expected.
+    Rank(Origin),
+    /// An operand is outside the domain of a verb.
+    Domain(Origin),
+    /// Operands have mismatched lengths or an index is out of range.
+    Length(Origin),
+    /// A name has no value.
+    Value(Origin),
+    /// The source could not be tokenized or parsed.
+    Parse(Origin),
+    /// Lambda calls nested deeper than the runtime allows.
+    Stack(Origin),
+    /// Reading the source failed.
+    Io(Origin),
+}
+
+impl KError {
+    pub(crate) fn rank() -> Self {
+        Self::Rank(Origin::new())
+    }
+
+    pub(crate) fn domain() -> Self {
+        Self::Domain(Origin::new())
+    }
+
+    pub(crate) fn length() -> Self {
+        Self::Length(Origin::new())
+    }
+
+    pub(crate) fn value() -> Self {
+        Self::Value(Origin::new())
+    }
+
+    pub(crate) fn parse() -> Self {
+        Self::Parse(Origin::new())
+    }
+
+    pub(crate) fn stack() -> Self {
+        Self::Stack(Origin::new())
+    }
+
+    pub(crate) fn io() -> Self {
+        Self::Io(Origin::new())
+    }
+
+    /// Attach the verb that raised the error, unless it already has one.
+    pub(crate) fn with_verb(mut self, verb: &'static str) -> Self {
+        self.origin_mut().verb.get_or_insert(verb);
+        self
+    }
+
+    /// Attach a source span to the error, unless it already has one: the
+    /// innermost span is the most precise.
+    pub(crate) fn with_span(mut self, span: Span) -> Self {
+        self.origin_mut().span.get_or_insert(span);
+        self
+    }
+
+    /// Remove the source span from the error.
+    pub(crate) fn without_span(mut self) -> Self {
+        self.origin_mut().span = None;
+        self
+    }
+
+    /// The short k name of the error kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Rank(_) => "rank",
+            Self::Domain(_) => "domain",
+            Self::Length(_) => "length",
+            Self::Value(_) => "value",
+            Self::Parse(_) => "parse",
+            Self::Stack(_) => "stack",
+            Self::Io(_) => "io",
+        }
+    }
+
+    /// Where the error was raised.
+    pub fn origin(&self) -> &Origin {
+        match self {
+            Self::Rank(origin)
+            | Self::Domain(origin)
+            | Self::Length(origin)
+            | Self::Value(origin)
+            | Self::Parse(origin)
+            | Self::Stack(origin)
+            | Self::Io(origin) => origin,
+        }
+    }
+
+    fn origin_mut(&mut self) -> &mut Origin {
+        match self {
+            Self::Rank(origin)
+            | Self::Domain(origin)
+            | Self::Length(origin)
+            | Self::Value(origin)
+            | Self::Parse(origin)
+            | Self::Stack(origin)
+            | Self::Io(origin) => origin,
+        }
+    }
+}
+
+impl Display for KError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.origin().verb {
+            Some(verb) => write!(f, "{} {}", verb, self.kind()),
+            None => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+impl std::error::Error for KError {}
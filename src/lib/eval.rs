@@ -0,0 +1,258 @@
+use crate::error::{KError, Span};
+use crate::parse::{Function, Node, Operand, parse};
+use crate::runtime::{Runtime, apply_adverb, apply_dyadic_verb, apply_monadic_verb, call, verb_of};
+use crate::token::{Control, Token};
+use crate::value::{Value, Verb};
+use std::rc::Rc;
+
+/// Evaluate a node of the syntax tree. Arguments are evaluated right to
+/// left, as in k: the right argument of a verb before its left one, and the
+/// arguments of a call before the function.
+pub(crate) fn evaluate(runtime: &mut Runtime, node: &Node) -> Result<Value, KError> {
+    let mut chain = Vec::new();
+    let mut node = node;
+    while let Some(right) = node.right_argument() {
+        chain.push(node);
+        node = right;
+    }
+    let mut value = match node {
+        Node::Noun(token, span) => runtime
+            .noun_from_token(token)
+            .map_err(|error| error.with_span(*span))?,
+        Node::List(items) => match items.as_slice() {
+            [] => Value::List(Rc::new(Vec::new())),
+            _ => Value::from_items(evaluate_items(runtime, items)?),
+        },
+        Node::Apply(function, arguments, span) => {
+            let arguments = evaluate_items(runtime, arguments)?;
+            let function = evaluate(runtime, function)?;
+            call(runtime, function, arguments).map_err(|error| error.with_span(*span))?
+        }
+        Node::Control(control, span, items) => {
+            evaluate_control(runtime, *control, items).map_err(|error| error.with_span(*span))?
+        }
+        Node::Block(statements) => {
+            let mut result = Value::List(Rc::new(Vec::new()));
+            for statement in statements {
+                result = evaluate(runtime, statement)?;
+            }
+            result
+        }
+        Node::Function(function) => Value::Verb(Rc::new(evaluate_function(runtime, function)?)),
+        _ => unreachable!("Node::right_argument covers the other nodes"),
+    };
+    for node in chain.into_iter().rev() {
+        value = complete(runtime, node, value).map_err(|error| error.with_span(span(node)))?;
+    }
+    Ok(value)
+}
+
+/// The span an error raised by a node that takes a right argument is
+/// reported at.
+fn span(node: &Node) -> Span {
+    match node {
+        Node::Monad(_, span, _)
+        | Node::Dyad(_, span, _, _)
+        | Node::Derived { span, .. }
+        | Node::Assign { span, .. }
+        | Node::Apply(_, _, span) => *span,
+        _ => unreachable!("only nodes with a right argument are completed"),
+    }
+}
+
+/// Finish evaluating a node that takes a right argument, once its value is
+/// known.
+fn complete(runtime: &mut Runtime, node: &Node, right_value: Value) -> Result<Value, KError> {
+    match node {
+        Node::Monad(verb, _, _) => apply_monadic_verb(runtime, *verb, right_value),
+        Node::Dyad(verb, _, left, _) => {
+            let left_value = evaluate(runtime, left)?;
+            apply_dyadic_verb(runtime, *verb, left_value, right_value)
+        }
+        Node::Derived {
+            operand,
+            adverb,
+            left,
+            ..
+        } => {
+            let verb = evaluate_operand(runtime, operand)?;
+            let left_value = match left {
+                Some(left) => Some(evaluate(runtime, left)?),
+                None => None,
+            };
+            apply_adverb(runtime, *adverb, &verb, left_value, right_value)
+        }
+        Node::Assign {
+            name, global, path, ..
+        } => match path {
+            Some(path) => {
+                let path = evaluate_items(runtime, path)?;
+                runtime.amend(name, &path, right_value)
+            }
+            None if *global => Ok(runtime.assign_global(name.clone(), right_value)),
+            None => Ok(runtime.assign(name.clone(), right_value)),
+        },
+        Node::Apply(function, _, _) => {
+            let function = evaluate(runtime, function)?;
+            call(runtime, function, vec![right_value])
+        }
+        _ => unreachable!("only nodes with a right argument are completed"),
+    }
+}
+
+/// Evaluate the operand of an adverb to the verb it modifies.
+fn evaluate_operand(runtime: &mut Runtime, operand: &Operand) -> Result<Verb, KError> {
+    match operand {
+        Operand::Verb(verb) => Ok(Verb::Builtin(*verb)),
+        Operand::Noun(noun) => verb_of(evaluate(runtime, noun)?),
+        Operand::Derived(operand, adverb) => {
+            let verb = evaluate_operand(runtime, operand)?;
+            Ok(Verb::Derived(Rc::new(verb), *adverb))
+        }
+    }
+}
+
+/// Evaluate a verb written as a value. The verbs of a composition are
+/// evaluated right to left.
+fn evaluate_function(runtime: &mut Runtime, function: &Function) -> Result<Verb, KError> {
+    match function {
+        Function::Verb(verb) => Ok(Verb::Builtin(*verb)),
+        Function::Monadic(verb) => Ok(Verb::Monadic(*verb)),
+        Function::Derived(operand, adverb) => {
+            let verb = evaluate_operand(runtime, operand)?;
+            Ok(Verb::Derived(Rc::new(verb), *adverb))
+        }
+        Function::Projection(verb, left) => {
+            let left_value = evaluate(runtime, left)?;
+            Ok(Verb::Projection(
+                Rc::new(Verb::Builtin(*verb)),
+                vec![left_value],
+            ))
+        }
+        Function::Composition(functions) => {
+            let mut verbs = functions.iter().rev();
+            let Some(inner) = verbs.next() else {
+                return Err(KError::parse());
+            };
+            let mut composition = evaluate_function(runtime, inner)?;
+            for outer in verbs {
+                let outer = evaluate_function(runtime, outer)?;
+                composition = Verb::Composition(Rc::new(outer), Rc::new(composition));
+            }
+            Ok(composition)
+        }
+    }
+}
+
+/// Parse and run the statements of a line. Returns the value to print:
+/// none if the last statement is empty, an assignment or a loop.
+pub(crate) fn evaluate_line(
+    runtime: &mut Runtime,
+    tokens: &[(Token, Span)],
+) -> Result<Option<Value>, KError> {
+    let block = parse(tokens)?;
+    let result = runtime.run(&block)?;
+    if matches!(tokens.last(), Some((Token::Semicolon, _))) {
+        return Ok(None);
+    }
+    match &block {
+        Node::Block(statements) => match statements.last() {
+            None | Some(Node::Assign { .. }) => Ok(None),
+            Some(Node::Control(Control::Do | Control::While, _, _)) => Ok(None),
+            Some(_) => Ok(Some(result)),
+        },
+        _ => Ok(Some(result)),
+    }
+}
+
+/// Evaluate a control form given the unevaluated expressions between its
+/// brackets. Only the expressions that are needed are evaluated: the
+/// branches of a conditional that are not taken are skipped entirely.
+fn evaluate_control(
+    runtime: &mut Runtime,
+    control: Control,
+    items: &[Node],
+) -> Result<Value, KError> {
+    match control {
+        Control::Cond => evaluate_cond(runtime, items),
+        Control::Do => evaluate_do(runtime, items),
+        Control::While => evaluate_while(runtime, items),
+    }
+}
+
+/// `$[c;t;f]`: evaluate `t` if `c` is true and `f` otherwise. Further pairs,
+/// `$[c1;t1;c2;t2;f]`, are tested in order. Without a final `f` the result
+/// is the empty list when no condition holds.
+fn evaluate_cond(runtime: &mut Runtime, items: &[Node]) -> Result<Value, KError> {
+    if items.len() < 2 {
+        return Err(KError::rank());
+    }
+    let mut pairs = items.chunks_exact(2);
+    for pair in pairs.by_ref() {
+        if is_true(runtime, &pair[0])? {
+            return evaluate(runtime, &pair[1]);
+        }
+    }
+    match pairs.remainder() {
+        [otherwise] => evaluate(runtime, otherwise),
+        _ => Ok(Value::List(Rc::new(Vec::new()))),
+    }
+}
+
+/// `do[n;e1;e2;...]`: evaluate the expressions in order `n` times.
+fn evaluate_do(runtime: &mut Runtime, items: &[Node]) -> Result<Value, KError> {
+    let [count, body @ ..] = items else {
+        return Err(KError::rank());
+    };
+    let Value::Atom(count) = evaluate(runtime, count)? else {
+        return Err(KError::domain());
+    };
+    for _ in 0..count {
+        for statement in body {
+            evaluate(runtime, statement)?;
+        }
+    }
+    Ok(Value::List(Rc::new(Vec::new())))
+}
+
+/// `while[c;e1;e2;...]`: evaluate the expressions in order as long as `c`
+/// is true, testing it before each pass.
+fn evaluate_while(runtime: &mut Runtime, items: &[Node]) -> Result<Value, KError> {
+    let [condition, body @ ..] = items else {
+        return Err(KError::rank());
+    };
+    while is_true(runtime, condition)? {
+        for statement in body {
+            evaluate(runtime, statement)?;
+        }
+    }
+    Ok(Value::List(Rc::new(Vec::new())))
+}
+
+/// Evaluate a condition and test it with `truth`.
+fn is_true(runtime: &mut Runtime, condition: &Node) -> Result<bool, KError> {
+    let value = evaluate(runtime, condition)?;
+    truth(value)
+}
+
+/// Test a condition. A number or a char is true when it is not zero;
+/// anything else is a domain error.
+pub(crate) fn truth(value: Value) -> Result<bool, KError> {
+    match value {
+        Value::Atom(value) => Ok(value != 0),
+        Value::Float(value) => Ok(value != 0.0),
+        Value::Char(value) => Ok(value != 0),
+        _ => Err(KError::domain()),
+    }
+}
+
+/// Evaluate list items or arguments right to left, returning them in order.
+fn evaluate_items(runtime: &mut Runtime, items: &[Node]) -> Result<Vec<Value>, KError> {
+    let mut values = items
+        .iter()
+        .rev()
+        .map(|item| evaluate(runtime, item))
+        .collect::<Result<Vec<_>, _>>()?;
+    values.reverse();
+    Ok(values)
+}
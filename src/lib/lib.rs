@@ -1,7 +1,14 @@
+mod compile;
+mod error;
+mod eval;
+mod parse;
 mod repl;
 mod runtime;
 mod token;
 mod value;
+mod vm;
 
+pub use error::{KError, Origin, Span};
 pub use repl::{run_batch, run_repl};
 pub use runtime::Runtime;
+pub use value::{Dict, Lambda, Value, Verb};
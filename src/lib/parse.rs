@@ -0,0 +1,496 @@
+use crate::error::{KError, Span};
+use crate::token::{Control, Token, verb_index};
+use std::mem::discriminant;
+use std::rc::Rc;
+
+/// A node of the syntax tree built by `parse`. Nodes that can raise an
+/// error carry the span of the token it is reported at.
+#[derive(Clone, Debug)]
+pub(crate) enum Node {
+    /// A noun held by a single token: a literal, symbols or a name.
+    Noun(Token, Span),
+    /// A list, `(a;b;c)`.
+    List(Vec<Node>),
+    /// A verb, by its index in `VERB_TOKENS`, applied to one argument: `-x`.
+    Monad(usize, Span, Box<Node>),
+    /// A verb applied to two arguments: `x+y`.
+    Dyad(usize, Span, Box<Node>, Box<Node>),
+    /// A verb derived with an adverb, applied to a right argument and
+    /// optionally a left one: `+/x`, `x,/:y`. The span is that of the verb,
+    /// or of the adverb if the operand is a noun.
+    Derived {
+        operand: Operand,
+        adverb: usize,
+        span: Span,
+        left: Option<Box<Node>>,
+        right: Box<Node>,
+    },
+    /// An assignment, `a:x`, a global assignment, `a::x`, or an amend,
+    /// `a[i]:x`. The span is that of the name.
+    Assign {
+        name: Rc<str>,
+        span: Span,
+        global: bool,
+        path: Option<Vec<Node>>,
+        value: Box<Node>,
+    },
+    /// A call or an index, `f[a;b]`, or an application by juxtaposition,
+    /// `f x`. The span is that of the first token of the function.
+    Apply(Box<Node>, Vec<Node>, Span),
+    /// A control form and the expressions between its brackets.
+    Control(Control, Span, Vec<Node>),
+    /// Statements, evaluated in order.
+    Block(Vec<Node>),
+    /// A verb as a value.
+    Function(Function),
+}
+
+impl Node {
+    /// The right argument of a node that takes one. Both engines follow the
+    /// chain of right arguments in a loop and then complete the nodes along
+    /// it, so that a long expression such as `1+2+...+n` does not recurse
+    /// once per verb.
+    pub(crate) fn right_argument(&self) -> Option<&Node> {
+        match self {
+            Self::Monad(_, _, right) | Self::Dyad(_, _, _, right) | Self::Derived { right, .. } => {
+                Some(right)
+            }
+            Self::Assign { value, .. } => Some(value),
+            Self::Apply(_, arguments, _) => match arguments.as_slice() {
+                [argument] => Some(argument),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A verb written as a value rather than applied: `+` or `+/` with nothing
+/// to its right or followed by brackets, or a chain of verbs such as `*|`
+/// or `2+-`, which composes them.
+#[derive(Clone, Debug)]
+pub(crate) enum Function {
+    /// A built-in verb, by its index in `VERB_TOKENS`: `+`.
+    Verb(usize),
+    /// A built-in verb applied monadically in a composition: the `*` of
+    /// `*|`.
+    Monadic(usize),
+    /// A verb derived with an adverb: `+/` or `f'`.
+    Derived(Operand, usize),
+    /// A dyadic verb with its left argument fixed: `2+`.
+    Projection(usize, Box<Node>),
+    /// Functions applied right to left, as written: `*|`.
+    Composition(Vec<Function>),
+}
+
+/// The operand of an adverb: a built-in verb, by its index in
+/// `VERB_TOKENS`, a noun that evaluates to a lambda, or a verb derived with
+/// another adverb, such as the `+/` of `+/'`.
+#[derive(Clone, Debug)]
+pub(crate) enum Operand {
+    Verb(usize),
+    Noun(Box<Node>),
+    Derived(Box<Operand>, usize),
+}
+
+/// A node waiting for its right argument while an expression is parsed.
+enum Pending {
+    Monad(usize, Span),
+    Dyad(usize, Span, Node),
+    Derived {
+        operand: Operand,
+        adverb: usize,
+        span: Span,
+        left: Option<Node>,
+    },
+    Assign {
+        name: Rc<str>,
+        span: Span,
+        global: bool,
+        path: Option<Vec<Node>>,
+    },
+    Apply(Node, Span),
+}
+
+impl Pending {
+    /// Complete the node with its right argument.
+    fn complete(self, right: Node) -> Node {
+        let right = Box::new(right);
+        match self {
+            Self::Monad(verb, span) => Node::Monad(verb, span, right),
+            Self::Dyad(verb, span, left) => Node::Dyad(verb, span, Box::new(left), right),
+            Self::Derived {
+                operand,
+                adverb,
+                span,
+                left,
+            } => Node::Derived {
+                operand,
+                adverb,
+                span,
+                left: left.map(Box::new),
+                right,
+            },
+            Self::Assign {
+                name,
+                span,
+                global,
+                path,
+            } => Node::Assign {
+                name,
+                span,
+                global,
+                path,
+                value: right,
+            },
+            Self::Apply(function, span) => Node::Apply(Box::new(function), vec![*right], span),
+        }
+    }
+}
+
+/// Take the verbs left waiting at the end of an expression, with nothing to
+/// their right, as a function: a single verb such as `+`, `+/` or `2+`, or a
+/// composition such as `*|`, whose verbs apply monadically.
+fn take_function(pending: &mut Vec<Pending>) -> Option<Function> {
+    let mut functions = Vec::new();
+    loop {
+        functions.push(match pending.pop() {
+            Some(Pending::Monad(verb, _)) => Function::Monadic(verb),
+            Some(Pending::Dyad(verb, _, left)) => Function::Projection(verb, Box::new(left)),
+            Some(Pending::Derived {
+                operand,
+                adverb,
+                left: None,
+                ..
+            }) => Function::Derived(operand, adverb),
+            Some(waiting) => {
+                pending.push(waiting);
+                break;
+            }
+            None => break,
+        });
+    }
+    functions.reverse();
+    match functions.len() {
+        0 => None,
+        1 => match functions.pop() {
+            Some(Function::Monadic(verb)) => Some(Function::Verb(verb)),
+            function => function,
+        },
+        _ => Some(Function::Composition(functions)),
+    }
+}
+
+/// Parse the `;`-separated statements of a line or a lambda body into a
+/// block. Empty statements are skipped.
+pub(crate) fn parse(tokens: &[(Token, Span)]) -> Result<Node, KError> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let block = parser.parse_block()?;
+    match parser.peek() {
+        None => Ok(block),
+        Some(_) => Err(parser.error()),
+    }
+}
+
+/// A recursive descent parser over the tokens of a line. Only brackets and
+/// parentheses recurse.
+struct Parser<'a> {
+    tokens: &'a [(Token, Span)],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    /// The span of the current token, or an empty span after the last one.
+    fn span(&self) -> Span {
+        match self.tokens.get(self.position) {
+            Some((_, span)) => *span,
+            None => {
+                let end = self.tokens.last().map_or(0, |(_, span)| span.end);
+                Span::new(end, end)
+            }
+        }
+    }
+
+    /// A parse error at the current token.
+    fn error(&self) -> KError {
+        KError::parse().with_span(self.span())
+    }
+
+    /// Returns true if the current token ends an expression.
+    fn at_end(&self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(Token::Semicolon | Token::RightParen | Token::RightBracket)
+        )
+    }
+
+    fn parse_block(&mut self) -> Result<Node, KError> {
+        let mut statements = Vec::new();
+        loop {
+            if !self.at_end() {
+                statements.push(self.parse_expression()?);
+            }
+            match self.peek() {
+                Some(Token::Semicolon) => self.position += 1,
+                _ => return Ok(Node::Block(statements)),
+            }
+        }
+    }
+
+    /// Parse an expression. A verb takes everything to its right as its
+    /// right argument, so the nodes waiting for it are kept on a stack and
+    /// completed once the rightmost noun is reached, rather than recursing
+    /// once per verb.
+    fn parse_expression(&mut self) -> Result<Node, KError> {
+        let mut pending = Vec::new();
+        let mut node = loop {
+            if let Some(Token::Symbol(verb)) = self.peek() {
+                let (verb, span) = self.parse_verb(*verb)?;
+                let derived = self
+                    .parse_adverb()
+                    .map(|adverb| self.parse_derived(Operand::Verb(verb), adverb));
+                // A verb followed by brackets is called or projected: `+[1]`.
+                if let Some(Token::LeftBracket) = self.peek() {
+                    let function = match derived {
+                        Some((operand, adverb)) => Function::Derived(operand, adverb),
+                        None => Function::Verb(verb),
+                    };
+                    let noun = self.parse_arguments(Node::Function(function), span)?;
+                    match self.parse_rest(noun, span, &mut pending)? {
+                        Some(node) => break node,
+                        None => continue,
+                    }
+                }
+                pending.push(match derived {
+                    Some((operand, adverb)) => Pending::Derived {
+                        operand,
+                        adverb,
+                        span,
+                        left: None,
+                    },
+                    None => Pending::Monad(verb, span),
+                });
+                continue;
+            }
+            if self.at_end()
+                && let Some(function) = take_function(&mut pending)
+            {
+                break Node::Function(function);
+            }
+            let (noun, span) = self.parse_noun()?;
+            if let Some(node) = self.parse_rest(noun, span, &mut pending)? {
+                break node;
+            }
+        };
+        while let Some(waiting) = pending.pop() {
+            node = waiting.complete(node);
+        }
+        Ok(node)
+    }
+
+    /// Parse what follows the noun `left`, whose first token is at
+    /// `left_span`: a verb applied to it dyadically, an adverb deriving a
+    /// verb from it, or an expression it applies to. Returns the noun if the
+    /// expression ends with it, and otherwise pushes the node waiting for the
+    /// rest of the expression.
+    fn parse_rest(
+        &mut self,
+        mut left: Node,
+        mut left_span: Span,
+        pending: &mut Vec<Pending>,
+    ) -> Result<Option<Node>, KError> {
+        loop {
+            let waiting = match self.peek() {
+                _ if self.at_end() => return Ok(Some(left)),
+                Some(Token::Colon) => self.parse_assign(left)?,
+                Some(Token::Symbol(verb)) => {
+                    let (verb, span) = self.parse_verb(*verb)?;
+                    match self.parse_adverb() {
+                        Some(adverb) => {
+                            let (operand, adverb) = self.parse_derived(Operand::Verb(verb), adverb);
+                            Pending::Derived {
+                                operand,
+                                adverb,
+                                span,
+                                left: Some(left),
+                            }
+                        }
+                        None => Pending::Dyad(verb, span, left),
+                    }
+                }
+                Some(Token::Adverb(adverb)) => {
+                    let span = self.span();
+                    self.position += 1;
+                    let (operand, adverb) =
+                        self.parse_derived(Operand::Noun(Box::new(left)), *adverb);
+                    // Like a derived built-in verb, it may be called: `g/[0;x]`.
+                    if let Some(Token::LeftBracket) = self.peek() {
+                        let function = Node::Function(Function::Derived(operand, adverb));
+                        left = self.parse_arguments(function, left_span)?;
+                        continue;
+                    }
+                    Pending::Derived {
+                        operand,
+                        adverb,
+                        span,
+                        left: None,
+                    }
+                }
+                _ => {
+                    let (noun, noun_span) = self.parse_noun()?;
+                    let span = self.span();
+                    match self.parse_adverb() {
+                        Some(adverb) => {
+                            let operand = Operand::Noun(Box::new(noun));
+                            let (operand, adverb) = self.parse_derived(operand, adverb);
+                            Pending::Derived {
+                                operand,
+                                adverb,
+                                span,
+                                left: Some(left),
+                            }
+                        }
+                        None => {
+                            pending.push(Pending::Apply(left, left_span));
+                            left = noun;
+                            left_span = noun_span;
+                            continue;
+                        }
+                    }
+                }
+            };
+            pending.push(waiting);
+            return Ok(None);
+        }
+    }
+
+    /// Parse an assignment once its target, `a` or `a[i]`, has been parsed
+    /// and a colon follows: `a:x`, `a::x` or `a[i]:x`.
+    fn parse_assign(&mut self, target: Node) -> Result<Pending, KError> {
+        let (name, span, path) = match target {
+            Node::Noun(Token::Name(name), span) => (name, span, None),
+            Node::Apply(name, path, _) => match *name {
+                Node::Noun(Token::Name(name), span) => (name, span, Some(path)),
+                _ => return Err(self.error()),
+            },
+            _ => return Err(self.error()),
+        };
+        self.position += 1;
+        let global = path.is_none() && matches!(self.peek(), Some(Token::Colon));
+        if global {
+            self.position += 1;
+        }
+        Ok(Pending::Assign {
+            name,
+            span,
+            global,
+            path,
+        })
+    }
+
+    /// Consume an adverb token, if there is one, and return its index in
+    /// `ADVERB_TOKENS`.
+    fn parse_adverb(&mut self) -> Option<usize> {
+        let Some(Token::Adverb(adverb)) = self.peek() else {
+            return None;
+        };
+        self.position += 1;
+        Some(*adverb)
+    }
+
+    /// Consume any further adverbs once `adverb` has been applied to
+    /// `operand`, and return the operand of the last one and its index. Each
+    /// adverb but the last derives a verb that the next one modifies: `+/'`
+    /// applies `+/` to each.
+    fn parse_derived(&mut self, mut operand: Operand, mut adverb: usize) -> (Operand, usize) {
+        while let Some(next) = self.parse_adverb() {
+            operand = Operand::Derived(Box::new(operand), adverb);
+            adverb = next;
+        }
+        (operand, adverb)
+    }
+
+    /// Consume a verb token and return its index in `VERB_TOKENS` and its
+    /// span.
+    fn parse_verb(&mut self, verb: u8) -> Result<(usize, Span), KError> {
+        match verb_index(verb) {
+            0 => Err(self.error()),
+            index => {
+                let span = self.span();
+                self.position += 1;
+                Ok((index, span))
+            }
+        }
+    }
+
+    /// Parse a noun: a single token, a parenthesized expression or list, or
+    /// a control form, followed by any bracketed arguments. Returns it with
+    /// the span of its first token.
+    fn parse_noun(&mut self) -> Result<(Node, Span), KError> {
+        let span = self.span();
+        let noun = match self.peek() {
+            Some(Token::LeftParen) => {
+                self.position += 1;
+                let mut items = self.parse_items(Token::RightParen)?;
+                match items.len() {
+                    1 => items.pop().unwrap(),
+                    _ => Node::List(items),
+                }
+            }
+            Some(Token::Control(control)) => {
+                self.position += 1;
+                if !matches!(self.peek(), Some(Token::LeftBracket)) {
+                    return Err(self.error());
+                }
+                self.position += 1;
+                Node::Control(*control, span, self.parse_items(Token::RightBracket)?)
+            }
+            Some(token @ (Token::Literal(_) | Token::Symbols(_) | Token::Name(_))) => {
+                self.position += 1;
+                Node::Noun(token.clone(), span)
+            }
+            _ => return Err(self.error()),
+        };
+        Ok((self.parse_arguments(noun, span)?, span))
+    }
+
+    /// Parse any bracketed arguments following `noun`, whose first token is
+    /// at `span`: `f[a;b]` or `a[i][j]`.
+    fn parse_arguments(&mut self, mut noun: Node, span: Span) -> Result<Node, KError> {
+        while let Some(Token::LeftBracket) = self.peek() {
+            self.position += 1;
+            let arguments = self.parse_items(Token::RightBracket)?;
+            noun = Node::Apply(Box::new(noun), arguments, span);
+        }
+        Ok(noun)
+    }
+
+    /// Parse `;`-separated items up to and including `closing`, once the
+    /// opening parenthesis or bracket has been consumed.
+    fn parse_items(&mut self, closing: Token) -> Result<Vec<Node>, KError> {
+        let is_closing = |token: &Token| discriminant(token) == discriminant(&closing);
+        let mut items = Vec::new();
+        if self.peek().is_some_and(is_closing) {
+            self.position += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.parse_expression()?);
+            match self.peek() {
+                Some(Token::Semicolon) => self.position += 1,
+                Some(token) if is_closing(token) => {
+                    self.position += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+}
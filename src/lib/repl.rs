@@ -1,67 +1,21 @@
-use crate::runtime::{Runtime, apply_adverb, apply_dyadic_verb, apply_monadic_verb};
-use crate::token::{Token, adverb_index, tokenize_line, verb_index};
-use crate::value::Value;
+use crate::compile::compile;
+use crate::error::{KError, Span};
+use crate::eval::evaluate_line;
+use crate::parse::parse;
+use crate::runtime::Runtime;
+use crate::token::{is_incomplete, tokenize, tokenize_line};
 use std::io::{self, BufRead, Write};
+use std::time::Instant;
 
-/// Evaluate an expression.
-fn evaluate_expression(runtime: &mut Runtime, tokens: &[Token]) -> Value {
-    match tokens {
-        [] => runtime.parse_error("evaluate_expression"),
-        [token] => match runtime.noun_from_token(token) {
-            Value::Error => runtime.parse_error("evaluate_error"),
-            value @ _ => value,
-        },
-        [Token::Symbol(verb), Token::Symbol(adverb), rest @ ..]
-            if verb_index(*verb) != 0 && adverb_index(*adverb) != 0 =>
-        {
-            let verb_idx = verb_index(*verb);
-            let adverb_idx = adverb_index(*adverb);
-            let operand = evaluate_expression(runtime, rest);
-            if operand.is_error() {
-                return operand;
-            }
-            apply_adverb(runtime, adverb_idx, verb_idx, operand)
-        }
-        [Token::Symbol(verb), rest @ ..] if verb_index(*verb) != 0 => {
-            let verb_idx = verb_index(*verb);
-            let operand = evaluate_expression(runtime, rest);
-            if operand.is_error() {
-                return operand;
-            }
-            apply_monadic_verb(runtime, verb_idx, operand)
-        }
-        [Token::Global(name), Token::Colon, rest @ ..] => {
-            let right_value = evaluate_expression(runtime, rest);
-            if right_value.is_error() {
-                return right_value;
-            }
-            let index = (name - b'a') as usize;
-            runtime.assign_global(index, right_value)
-        }
-        [left_token, Token::Symbol(op), rest @ ..] => {
-            let left_value = runtime.noun_from_token(left_token);
-            if left_value.is_error() {
-                return runtime.parse_error("evaluate_expression");
-            }
-
-            let right_value = evaluate_expression(runtime, rest);
-            if right_value.is_error() {
-                return right_value;
-            }
-
-            let dyadic_idx = verb_index(*op);
-            if dyadic_idx == 0 {
-                return runtime.domain_error("evaluate_expression");
-            }
-
-            apply_dyadic_verb(runtime, dyadic_idx, left_value, right_value)
-        }
-        _ => runtime.parse_error("evaluate_expression"),
-    }
+/// Where a line of k/simple code was read from: the name of the file, or
+/// `stdin`, and the number of its first input line, counting from 1.
+struct Location<'a> {
+    name: &'a str,
+    line: usize,
 }
 
-/// Process a line of k/simple code.
-fn process_line(runtime: &mut Runtime, line: &str) -> bool {
+/// Process a line of k/simple code, which may span several input lines.
+fn process_line(runtime: &mut Runtime, line: &str, location: &Location) -> bool {
     let trimmed = line.trim_end();
 
     if trimmed.is_empty() {
@@ -74,15 +28,35 @@ fn process_line(runtime: &mut Runtime, line: &str) -> bool {
     // \\ - quit
     // \w - memory allocation in workspace (in bytes, by vectors only)
     // \v - list global variables (vectors only)
+    // \b - switch between the bytecode VM and the tree walker
+    // \t e - time the expression e in milliseconds; \t:n e runs it n times
     if bytes.len() == 2 && bytes[0] == b'\\' {
         match bytes[1] {
             b'\\' => return false,
             b'w' => println!("{}", runtime.workspace_bytes()),
             b'v' => print!("{}", runtime),
+            b'b' => {
+                runtime.set_bytecode(!runtime.bytecode());
+                println!(
+                    "{}",
+                    if runtime.bytecode() {
+                        "bytecode"
+                    } else {
+                        "tree"
+                    }
+                );
+            }
             _ => {}
         }
         return true;
     }
+    if trimmed.starts_with("\\t ") || trimmed.starts_with("\\t:") {
+        match time_expression(runtime, trimmed) {
+            Ok(milliseconds) => println!("{}", milliseconds),
+            Err(error) => report_error(&error, trimmed, location),
+        }
+        return true;
+    }
 
     // Comments start with a slash.
     if bytes[0] == b'/' {
@@ -92,8 +66,8 @@ fn process_line(runtime: &mut Runtime, line: &str) -> bool {
     // Tokenize the line.
     let tokens = match tokenize_line(trimmed) {
         Ok(tokens) => tokens,
-        Err(_) => {
-            runtime.parse_error("tokenize_line");
+        Err(error) => {
+            report_error(&error, trimmed, location);
             return true;
         }
     };
@@ -103,32 +77,120 @@ fn process_line(runtime: &mut Runtime, line: &str) -> bool {
         return true;
     }
 
-    let result = evaluate_expression(runtime, &tokens);
+    // Print the value of the last statement, unless it is an assignment.
+    match evaluate_line(runtime, &tokens) {
+        Ok(Some(result)) => println!("{}", result),
+        Ok(None) => {}
+        Err(error) => report_error(&error, trimmed, location),
+    }
+
+    true
+}
 
-    // Assignment.
-    if tokens.len() > 1 && matches!(tokens[1], Token::Colon) {
-        return true;
+/// Run the expression of a `\t` command line and return the time it took
+/// in milliseconds. `\t:n` runs it `n` times. It is parsed, and compiled if
+/// the VM is in use, once before the clock starts, so that only running it
+/// is timed and the VM and the tree walker can be compared. Spans count
+/// from the start of the line, so errors point into the expression.
+fn time_expression(runtime: &mut Runtime, line: &str) -> Result<u128, KError> {
+    let (count, start) = match line.strip_prefix("\\t:") {
+        Some(rest) => {
+            let count = rest.split(' ').next().unwrap_or(rest);
+            let end = 3 + count.len();
+            let count = count
+                .parse::<u64>()
+                .map_err(|_| KError::parse().with_span(Span::new(3, end)))?;
+            (count, end)
+        }
+        None => (1, 2),
+    };
+    let block = parse(&tokenize(line, start)?)?;
+    let code = runtime.bytecode().then(|| compile(&block));
+    let clock = Instant::now();
+    for _ in 0..count {
+        match &code {
+            Some(code) => runtime.run_compiled(code)?,
+            None => runtime.run(&block)?,
+        };
+    }
+    Ok(clock.elapsed().as_millis())
+}
+
+/// Add an input line to `pending`. Returns the source to process once every
+/// bracket is closed, or `None` while the expression continues on the next
+/// line. Commands and comments are only recognized on a first line, and
+/// comment lines inside an expression are left empty, so that errors still
+/// report the right line.
+fn read_source(pending: &mut String, line: &str) -> Option<String> {
+    let line = line.trim_end();
+    if pending.is_empty() {
+        if line.starts_with(['/', '\\']) {
+            return Some(line.to_string());
+        }
+    } else {
+        pending.push('\n');
+        if line.trim_start().starts_with('/') {
+            return None;
+        }
     }
 
-    println!("{}", result);
+    pending.push_str(line);
+    match is_incomplete(pending) {
+        true => None,
+        false => Some(std::mem::take(pending)),
+    }
+}
 
-    true
+/// Print an error raised while processing `source`, prefixed with the file
+/// name, line and column it was raised at. The offending input line is
+/// echoed with a caret under the token. An error without a span gives the
+/// line only.
+fn report_error(error: &KError, source: &str, location: &Location) {
+    let Some(span) = error.origin().span else {
+        println!("{}:{}: {}", location.name, location.line, error);
+        return;
+    };
+    let start = span.start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |index| start + index);
+    let line = location.line + source[..line_start].matches('\n').count();
+    let column = source[line_start..start].chars().count();
+    println!("{}:{}:{}: {}", location.name, line, column + 1, error);
+    println!("{}", &source[line_start..line_end]);
+    println!("{}^", " ".repeat(column));
 }
 
-/// Run a REPL.
+/// Run a REPL. An expression with unclosed brackets continues on the next
+/// line after a `..` prompt.
 pub fn run_repl(runtime: &mut Runtime) {
     let mut input = String::new();
+    let mut pending = String::new();
+    let mut location = Location {
+        name: "stdin",
+        line: 1,
+    };
+    let mut number = 0;
 
     loop {
-        print!("k)");
+        print!("{}", if pending.is_empty() { "k)" } else { ".." });
 
         let _ = io::stdout().flush();
         input.clear();
 
         if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            process_line(runtime, &pending, &location);
             break;
         }
-        if !process_line(runtime, &input) {
+        number += 1;
+        if pending.is_empty() {
+            location.line = number;
+        }
+        let Some(source) = read_source(&mut pending, &input) else {
+            continue;
+        };
+        if !process_line(runtime, &source, &location) {
             break;
         }
     }
@@ -139,24 +201,38 @@ pub fn run_batch(runtime: &mut Runtime, path: &str) {
     let file = match std::fs::File::open(path) {
         Ok(file) => file,
         Err(_) => {
-            runtime.report_error("read_line", path);
+            println!("{} {}", KError::io(), path);
             return;
         }
     };
 
     let reader = io::BufReader::new(file);
+    let mut pending = String::new();
+    let mut location = Location {
+        name: path,
+        line: 1,
+    };
 
-    for line in reader.lines() {
+    for (index, line) in reader.lines().enumerate() {
         match line {
             Ok(line) => {
-                if !process_line(runtime, &line) {
-                    break;
+                if pending.is_empty() {
+                    location.line = index + 1;
+                }
+                let Some(source) = read_source(&mut pending, &line) else {
+                    continue;
+                };
+                if !process_line(runtime, &source, &location) {
+                    return;
                 }
             }
             Err(_) => {
-                runtime.report_error("read_line", path);
-                break;
+                println!("{} {}", KError::io(), path);
+                return;
             }
         }
     }
+
+    // Report an expression left unfinished at the end of the file.
+    process_line(runtime, &pending, &location);
 }
@@ -1,28 +1,175 @@
-use crate::token::Token;
-use crate::value::Value;
-use std::collections::HashSet;
+use crate::compile::{Op, compile};
+use crate::error::KError;
+use crate::eval::evaluate;
+use crate::parse::{Node, parse};
+use crate::token::{Control, Token, VERB_TOKENS, tokenize_line, verb_token};
+use crate::value::{Dict, Lambda, Value, Verb};
+use crate::vm::execute;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::fmt::Display;
+use std::hash::Hash;
 use std::rc::Rc;
 
-type MonadicVerb = fn(&Runtime, Value) -> Value;
-type DyadicVerb = fn(&Runtime, Value, Value) -> Value;
-type Adverb = fn(&Runtime, usize, Value) -> Value;
+type MonadicVerb = fn(&Runtime, Value) -> Result<Value, KError>;
+type DyadicVerb = fn(&Runtime, Value, Value) -> Result<Value, KError>;
+type Adverb = fn(&mut Runtime, &Verb, Option<Value>, Value) -> Result<Value, KError>;
+
+impl Verb {
+    /// The number of arguments the verb takes: two for built-in verbs, which
+    /// adverbs apply dyadically. A derived verb takes one argument with over
+    /// and scan, as many as its verb with each, and two otherwise.
+    fn arity(&self) -> usize {
+        match self {
+            Verb::Builtin(_) => 2,
+            Verb::Monadic(_) => 1,
+            Verb::Lambda(lambda) => lambda.arity(),
+            Verb::Derived(_, 1 | 2) => 1,
+            Verb::Derived(verb, 3) => verb.arity(),
+            Verb::Derived(_, _) => 2,
+            Verb::Projection(verb, arguments) => verb.arity().saturating_sub(arguments.len()),
+            Verb::Composition(_, inner) => inner.arity(),
+        }
+    }
+
+    /// Apply the verb to one argument.
+    fn apply_monadic(&self, runtime: &mut Runtime, value: Value) -> Result<Value, KError> {
+        match self {
+            Verb::Builtin(verb_index) | Verb::Monadic(verb_index) => {
+                apply_monadic_verb(runtime, *verb_index, value)
+            }
+            Verb::Lambda(lambda) => runtime.call_lambda(lambda, vec![value]),
+            Verb::Derived(verb, adverb) => apply_adverb(runtime, *adverb, verb, None, value),
+            Verb::Projection(..) => self.call(runtime, vec![value]),
+            Verb::Composition(outer, inner) => {
+                let value = inner.apply_monadic(runtime, value)?;
+                outer.apply_monadic(runtime, value)
+            }
+        }
+    }
+
+    /// Apply the verb to two arguments.
+    fn apply_dyadic(
+        &self,
+        runtime: &mut Runtime,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, KError> {
+        match self {
+            Verb::Builtin(verb_index) => apply_dyadic_verb(runtime, *verb_index, left, right),
+            Verb::Monadic(_) => Err(KError::rank()),
+            Verb::Lambda(lambda) => runtime.call_lambda(lambda, vec![left, right]),
+            Verb::Derived(verb, adverb) => apply_adverb(runtime, *adverb, verb, Some(left), right),
+            Verb::Projection(..) => self.call(runtime, vec![left, right]),
+            Verb::Composition(outer, inner) => {
+                let value = inner.apply_dyadic(runtime, left, right)?;
+                outer.apply_monadic(runtime, value)
+            }
+        }
+    }
+
+    /// Call the verb with bracketed arguments, `f[x;y]`, or one argument by
+    /// juxtaposition, `f x`. Fewer arguments than the verb takes project it,
+    /// fixing the ones given, except that a derived verb applies to one or
+    /// two arguments alike.
+    pub(crate) fn call(
+        &self,
+        runtime: &mut Runtime,
+        arguments: Vec<Value>,
+    ) -> Result<Value, KError> {
+        if !arguments.is_empty()
+            && arguments.len() < self.arity()
+            && !matches!(self, Verb::Derived(..))
+        {
+            let verb = Rc::new(self.clone());
+            return Ok(Value::Verb(Rc::new(Verb::Projection(verb, arguments))));
+        }
+        match self {
+            Verb::Lambda(lambda) => runtime.call_lambda(lambda, arguments),
+            Verb::Projection(verb, fixed) => {
+                let arguments = fixed.iter().cloned().chain(arguments).collect();
+                verb.call(runtime, arguments)
+            }
+            Verb::Composition(outer, inner) => {
+                let value = inner.call(runtime, arguments)?;
+                outer.apply_monadic(runtime, value)
+            }
+            _ => {
+                let mut arguments = arguments.into_iter();
+                match (arguments.next(), arguments.next(), arguments.next()) {
+                    (Some(value), None, None) => self.apply_monadic(runtime, value),
+                    (Some(left), Some(right), None) => self.apply_dyadic(runtime, left, right),
+                    _ => Err(KError::rank()),
+                }
+            }
+        }
+    }
+}
+
+/// The verb a value stands for when an adverb modifies it: a lambda or a
+/// verb. Anything else is a domain error.
+pub(crate) fn verb_of(value: Value) -> Result<Verb, KError> {
+    match value {
+        Value::Lambda(lambda) => Ok(Verb::Lambda(lambda)),
+        Value::Verb(verb) => Ok(Rc::unwrap_or_clone(verb)),
+        _ => Err(KError::domain()),
+    }
+}
+
+/// Apply a value to arguments: call a lambda or a verb, and index anything
+/// else.
+pub(crate) fn call(
+    runtime: &mut Runtime,
+    function: Value,
+    arguments: Vec<Value>,
+) -> Result<Value, KError> {
+    match function {
+        Value::Lambda(lambda) => Verb::Lambda(lambda).call(runtime, arguments),
+        Value::Verb(verb) => verb.call(runtime, arguments),
+        function => index_path(function, &arguments),
+    }
+}
+
+/// The default for how deeply lambda calls may nest. Each call uses up to
+/// about 15 KB of native stack in a debug build, so 100 calls fit the 2 MB
+/// stack of a spawned thread with room to spare.
+const MAX_DEPTH: usize = 100;
+
+/// The local variables of a lambda call in progress, starting with its
+/// arguments `x`, `y` and `z`.
+struct Frame {
+    locals: HashMap<Rc<str>, Value>,
+}
 
 /// The runtime environment.
 pub struct Runtime {
-    /// The global variables: a-z.
-    globals: [Value; 26],
+    /// The global variables, by name.
+    globals: HashMap<Rc<str>, Value>,
+    /// The interned symbol names.
+    symbols: HashSet<Rc<str>>,
+    /// The lambda calls in progress, innermost last. Each call has its own
+    /// frame, so recursive calls don't clobber each other's locals.
+    frames: Vec<Frame>,
+    /// Whether lines and lambdas run as bytecode on the VM rather than by
+    /// walking their syntax tree.
+    bytecode: bool,
+    /// The deepest lambda calls may nest before a stack error.
+    max_depth: usize,
 }
 
 /// Display the runtime environment.
 impl Display for Runtime {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (index, value) in self.globals.iter().enumerate() {
-            if let Value::Vector(vector) = value {
-                let name = (b'a' + index as u8) as char;
-                let length = vector.len();
-                let ref_count = Rc::strong_count(vector);
-                writeln!(f, "{}[{}] {}", name, length, ref_count)?;
+        let mut names = self.globals.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            if let Some(allocation) = self.globals[name].allocation() {
+                writeln!(
+                    f,
+                    "{}[{}] {}",
+                    name, allocation.length, allocation.ref_count
+                )?;
             }
         }
         Ok(())
@@ -33,361 +180,1051 @@ impl Runtime {
     /// Create a new runtime environment.
     pub fn new() -> Self {
         Self {
-            globals: std::array::from_fn(|_| Value::Atom(0)),
+            globals: HashMap::new(),
+            symbols: HashSet::new(),
+            frames: Vec::new(),
+            bytecode: true,
+            max_depth: MAX_DEPTH,
+        }
+    }
+
+    /// Evaluate k/simple source and return the value of its last statement.
+    /// Statements are separated by `;` or line breaks.
+    ///
+    /// Evaluation recurses on the native stack of the calling thread, most
+    /// deeply through nested lambda calls. Calls nested deeper than
+    /// [`Runtime::max_depth`] are a stack error, and the default limit is
+    /// safe on a 2 MB thread stack; raise it with [`Runtime::set_max_depth`]
+    /// only when running on a larger stack.
+    ///
+    /// ```
+    /// use ksimple::{Runtime, Value};
+    ///
+    /// let mut runtime = Runtime::new();
+    /// runtime.set_global("x", vec![1, 2, 3])?;
+    ///
+    /// assert_eq!(runtime.eval("+/x*x")?, Value::Atom(14));
+    /// assert_eq!(runtime.global("x"), Some(vec![1, 2, 3].into()));
+    /// println!("{} bytes", runtime.workspace_bytes());
+    /// # Ok::<(), ksimple::KError>(())
+    /// ```
+    pub fn eval(&mut self, source: &str) -> Result<Value, KError> {
+        let tokens = tokenize_line(source)?;
+        self.run(&parse(&tokens)?)
+    }
+
+    /// Returns true if code runs as bytecode on the VM, and false if it is
+    /// evaluated by walking its syntax tree.
+    pub fn bytecode(&self) -> bool {
+        self.bytecode
+    }
+
+    /// Choose between the bytecode VM and the tree walker. Both give the
+    /// same results; the VM is the default.
+    pub fn set_bytecode(&mut self, bytecode: bool) {
+        self.bytecode = bytecode;
+    }
+
+    /// Returns how deeply lambda calls may nest before a stack error.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Set how deeply lambda calls may nest. A deeper limit needs a larger
+    /// native stack: one that is too small for it aborts the process rather
+    /// than raising a stack error.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Evaluate a parsed line with the VM or the tree walker.
+    pub(crate) fn run(&mut self, block: &Node) -> Result<Value, KError> {
+        match self.bytecode {
+            true => self.run_compiled(&compile(block)),
+            false => evaluate(self, block),
+        }
+    }
+
+    /// Run a line already compiled to bytecode on the VM.
+    pub(crate) fn run_compiled(&mut self, code: &[Op]) -> Result<Value, KError> {
+        execute(self, code)
+    }
+
+    /// Get the value of the global variable `name`, if it is defined.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.globals.get(name).cloned()
+    }
+
+    /// Set the global variable `name` to `value`. The name must be a letter
+    /// followed by letters and digits.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) -> Result<(), KError> {
+        if !is_name(name) {
+            return Err(KError::domain());
         }
+        self.assign_global(Rc::from(name), value.into());
+        Ok(())
     }
 
-    /// Get the total size of allocated memory for vectors in a workspace.
-    pub(crate) fn workspace_bytes(&self) -> usize {
-        let mut seen: HashSet<*const Vec<i64>> = HashSet::new();
+    /// Get the total size of allocated memory for vectors in a workspace,
+    /// including vectors nested in lists.
+    pub fn workspace_bytes(&self) -> usize {
+        let mut seen: HashSet<*const ()> = HashSet::new();
         let mut total = 0;
 
-        for value in &self.globals {
-            if let Value::Vector(vector) = value {
-                let ptr = Rc::as_ptr(vector);
-                if seen.insert(ptr) {
-                    total += vector.len();
+        for value in self.globals.values() {
+            for allocation in value.nested_allocations() {
+                if seen.insert(allocation.address) {
+                    total += allocation.bytes;
                 }
             }
         }
 
-        total * std::mem::size_of::<i64>()
+        total
     }
 
-    /// Convert a token to a value and take ownership of it.
-    pub(crate) fn noun_from_token(&mut self, token: &Token) -> Value {
+    /// Convert a token to a value and take ownership of it. A name is looked
+    /// up in the locals of the innermost lambda call, then in the globals.
+    pub(crate) fn noun_from_token(&mut self, token: &Token) -> Result<Value, KError> {
         match token {
-            Token::Number(value) => Value::Atom(*value),
-            Token::Global(name) => {
-                let index = (name - b'a') as usize;
-                self.globals[index].clone()
-            }
-            _ => Value::Error,
+            Token::Literal(value) => Ok(value.clone()),
+            Token::Symbols(names) => match names.as_slice() {
+                [name] => Ok(Value::Symbol(self.intern(name))),
+                _ => Ok(names
+                    .iter()
+                    .map(|name| self.intern(name))
+                    .collect::<Vec<_>>()
+                    .into()),
+            },
+            Token::Name(name) => self
+                .frames
+                .last()
+                .and_then(|frame| frame.locals.get(name))
+                .or_else(|| self.globals.get(name))
+                .cloned()
+                .ok_or_else(KError::value),
+            _ => Err(KError::parse()),
         }
     }
 
-    /// Assign a value to a global variable.
-    pub(crate) fn assign_global(&mut self, index: usize, value: Value) -> Value {
-        self.globals[index] = value.clone();
-        value
+    /// Return the shared name for a symbol, interning it on first use.
+    pub(crate) fn intern(&mut self, name: &str) -> Rc<str> {
+        if let Some(symbol) = self.symbols.get(name) {
+            return symbol.clone();
+        }
+        let symbol: Rc<str> = Rc::from(name);
+        self.symbols.insert(symbol.clone());
+        symbol
     }
 
-    /// Report an error.
-    #[track_caller]
-    pub(crate) fn report_error(&self, function_name: &str, message: &str) -> Value {
-        let line = std::panic::Location::caller().line();
-        println!("{}:{} {}\n", function_name, line, message);
-        Value::Error
+    /// Call `lambda` with `arguments`, which must match its arity, in a
+    /// frame of its own. Calls nested deeper than `max_depth` are a stack
+    /// error.
+    pub(crate) fn call_lambda(
+        &mut self,
+        lambda: &Lambda,
+        arguments: Vec<Value>,
+    ) -> Result<Value, KError> {
+        if arguments.len() != lambda.arity() {
+            return Err(KError::rank());
+        }
+        if self.frames.len() >= self.max_depth {
+            return Err(KError::stack());
+        }
+        let locals = ["x", "y", "z"]
+            .into_iter()
+            .map(Rc::from)
+            .zip(arguments)
+            .collect();
+        self.frames.push(Frame { locals });
+        let result = match self.bytecode {
+            true => execute(self, lambda.code()),
+            false => evaluate(self, lambda.body()),
+        };
+        self.frames.pop();
+        // The spans of errors in the body point into the line the lambda was
+        // written on, so the error is reported where it is called instead.
+        result.map_err(KError::without_span)
     }
 
-    #[track_caller]
-    pub(crate) fn rank_error(&self, function_name: &str) -> Value {
-        self.report_error(function_name, "rank")
+    /// Assign a value to a variable: a local inside a lambda, shadowing any
+    /// global of the same name, and a global otherwise.
+    pub(crate) fn assign(&mut self, name: Rc<str>, value: Value) -> Value {
+        match self.frames.last_mut() {
+            Some(frame) => {
+                frame.locals.insert(name, value.clone());
+                value
+            }
+            None => self.assign_global(name, value),
+        }
     }
 
-    #[track_caller]
-    pub(crate) fn domain_error(&self, function_name: &str) -> Value {
-        self.report_error(function_name, "domain")
-    }
+    /// Amend the variable `name` at depth `path` with `value`, as in
+    /// `a[i]:v`. A local is amended if the innermost lambda call has one,
+    /// otherwise the global. The variable is updated in place when nothing
+    /// else shares it.
+    pub(crate) fn amend(
+        &mut self,
+        name: &Rc<str>,
+        path: &[Value],
+        value: Value,
+    ) -> Result<Value, KError> {
+        let is_local = self
+            .frames
+            .last()
+            .is_some_and(|frame| frame.locals.contains_key(name));
+        let target = match is_local {
+            true => self
+                .frames
+                .last_mut()
+                .and_then(|frame| frame.locals.get_mut(name)),
+            false => self.globals.get_mut(name),
+        }
+        .ok_or_else(KError::value)?;
 
-    #[track_caller]
-    fn length_error(&self, function_name: &str) -> Value {
-        self.report_error(function_name, "length")
+        if target.is_atom() {
+            return Err(KError::rank());
+        }
+        target.amend(path, &value)?;
+        Ok(value)
     }
 
-    #[track_caller]
-    pub(crate) fn parse_error(&self, function_name: &str) -> Value {
-        self.report_error(function_name, "parse")
+    /// Assign a value to a global variable.
+    pub(crate) fn assign_global(&mut self, name: Rc<str>, value: Value) -> Value {
+        self.globals.insert(name, value.clone());
+        value
     }
+}
 
-    #[track_caller]
-    pub(crate) fn not_implemented_error(&self, function_name: &str) -> Value {
-        self.report_error(function_name, "nyi")
+/// Returns true if `name` is a valid variable name: a letter followed by
+/// letters and digits, other than a reserved word.
+fn is_name(name: &str) -> bool {
+    name.bytes()
+        .next()
+        .is_some_and(|byte| byte.is_ascii_alphabetic())
+        && name.bytes().all(|byte| byte.is_ascii_alphanumeric())
+        && Control::from_name(name).is_none()
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-fn monadic_not_a_verb(runtime: &Runtime, _value: Value) -> Value {
-    runtime.domain_error("monadic_not_a_verb")
+fn monadic_not_a_verb(_runtime: &Runtime, _value: Value) -> Result<Value, KError> {
+    Err(KError::domain())
+}
+
+fn dyadic_not_a_verb(_runtime: &Runtime, _left: Value, _right: Value) -> Result<Value, KError> {
+    Err(KError::domain())
 }
 
-fn dyadic_not_a_verb(runtime: &Runtime, _left: Value, _right: Value) -> Value {
-    runtime.domain_error("dyadic_not_a_verb")
+/// Flip `value`: transpose a list of equal-length rows, extending atoms.
+/// A flat vector is its own transpose. A dictionary of columns flips into a
+/// table and a table back into its dictionary of columns.
+fn monadic_flip(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    let rows = match &value {
+        Value::List(rows) => rows,
+        Value::Dict(dict) => return table_from_dict(dict),
+        Value::Table(table) => return Ok(Value::Dict(table.clone())),
+        _ => {
+            return match value.len() {
+                Some(_) => Ok(value),
+                None => Err(KError::rank()),
+            };
+        }
+    };
+
+    let mut width = None;
+    for length in rows.iter().filter_map(Value::len) {
+        if width.is_some_and(|width| width != length) {
+            return Err(KError::length());
+        }
+        width = Some(length);
+    }
+    let Some(width) = width else {
+        return Ok(value);
+    };
+
+    Ok(Value::List(Rc::new(
+        (0..width)
+            .map(|column| {
+                Value::from_items(
+                    rows.iter()
+                        .map(|row| row.item(column).unwrap_or_else(|| row.clone()))
+                        .collect(),
+                )
+            })
+            .collect(),
+    )))
 }
 
-fn monadic_not_implemented(runtime: &Runtime, _value: Value) -> Value {
-    runtime.not_implemented_error("monadic_not_implemented")
+/// Make a table from a dictionary of symbol keys and equal-length columns,
+/// extending atom columns. The columns are shared with the dictionary.
+fn table_from_dict(dict: &Rc<Dict>) -> Result<Value, KError> {
+    if !matches!(dict.keys(), Value::SymbolVector(_)) {
+        return Err(KError::domain());
+    }
+
+    let columns = dict.values().items();
+    let mut rows = None;
+    for length in columns.iter().filter_map(Value::len) {
+        if rows.is_some_and(|rows| rows != length) {
+            return Err(KError::length());
+        }
+        rows = Some(length);
+    }
+    let Some(rows) = rows else {
+        return Err(KError::rank());
+    };
+
+    if matches!(dict.values(), Value::List(_)) && columns.iter().all(|column| !column.is_atom()) {
+        return Ok(Value::Table(dict.clone()));
+    }
+    let columns = columns
+        .into_iter()
+        .map(|column| match column.is_atom() {
+            true => take(rows, column),
+            false => column,
+        })
+        .collect();
+    Ok(Value::Table(Rc::new(Dict::new(
+        dict.keys().clone(),
+        Value::List(Rc::new(columns)),
+    ))))
 }
 
 /// Negate `value`.
-fn monadic_negate(_runtime: &Runtime, value: Value) -> Value {
-    -value
+fn monadic_negate(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    value.negate().map_err(|_| KError::domain())
 }
 
-/// Enumerate `value`.
-fn monadic_enumerate(runtime: &Runtime, value: Value) -> Value {
+/// Enumerate `value`, or return the keys of a dictionary.
+fn monadic_enumerate(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
     match value {
+        Value::Dict(dict) | Value::Table(dict) => Ok(dict.keys().clone()),
         Value::Atom(integer) => match integer {
-            ..0 => runtime.domain_error("monadic_enumerate"),
-            _ => (0..integer).map(|i| i as i64).collect::<Vec<_>>().into(),
+            ..0 => Err(KError::domain()),
+            _ => Ok((0..integer).collect::<Vec<_>>().into()),
         },
-        Value::Vector(_) => runtime.rank_error("monadic_enumerate"),
-        Value::Error => Value::Error,
+        _ if value.is_atom() => Err(KError::domain()),
+        _ => Err(KError::rank()),
     }
 }
 
 /// Return the length of `value`.
-fn monadic_count(runtime: &Runtime, value: Value) -> Value {
+fn monadic_count(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    value.len().map(Value::from).ok_or_else(KError::rank)
+}
+
+/// Enlist `value`.
+fn monadic_enlist(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    Ok(value.enlist())
+}
+
+/// Return the first element of `value`, or the first value of a dictionary.
+fn monadic_first(runtime: &Runtime, value: Value) -> Result<Value, KError> {
     match value {
-        Value::Atom(_) => runtime.rank_error("monadic_count"),
-        Value::Vector(vector) => vector.len().into(),
-        Value::Error => Value::Error,
+        Value::Dict(dict) => dyadic_index_at(runtime, dict.values().clone(), 0_i64.into()),
+        _ => dyadic_index_at(runtime, value, 0_i64.into()),
     }
 }
 
-/// Enlist `value`.
-fn monadic_enlist(runtime: &Runtime, value: Value) -> Value {
+/// Number the distinct keys in order of first occurrence.
+fn group_indices<K: Hash + Eq>(keys: impl Iterator<Item = K>) -> Vec<i64> {
+    let mut groups: HashMap<K, i64> = HashMap::new();
+    keys.map(|key| {
+        let next = groups.len() as i64;
+        *groups.entry(key).or_insert(next)
+    })
+    .collect()
+}
+
+/// Group `value` into the indices of each distinct item, in order of first
+/// occurrence. `=n` is the n by n identity matrix.
+fn monadic_group(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    let group_ids = match &value {
+        Value::Atom(size) if *size >= 0 => {
+            return Ok(Value::List(Rc::new(
+                (0..*size)
+                    .map(|row| {
+                        (0..*size)
+                            .map(|column| (row == column) as i64)
+                            .collect::<Vec<_>>()
+                            .into()
+                    })
+                    .collect(),
+            )));
+        }
+        Value::Atom(_) => return Err(KError::domain()),
+        Value::Vector(vector) => group_indices(vector.iter()),
+        // Adding 0.0 folds -0.0 into 0.0 so both land in the same group.
+        Value::FloatVector(vector) => {
+            group_indices(vector.iter().map(|float| (float + 0.0).to_bits()))
+        }
+        Value::CharVector(vector) => group_indices(vector.iter()),
+        Value::SymbolVector(vector) => group_indices(vector.iter()),
+        Value::List(list) => {
+            let mut distinct: Vec<&Value> = Vec::new();
+            list.iter()
+                .map(
+                    |item| match distinct.iter().position(|other| *other == item) {
+                        Some(position) => position as i64,
+                        None => {
+                            distinct.push(item);
+                            distinct.len() as i64 - 1
+                        }
+                    },
+                )
+                .collect()
+        }
+        _ => return Err(KError::rank()),
+    };
+
+    let mut groups: Vec<Vec<i64>> = Vec::new();
+    for (index, group_id) in group_ids.into_iter().enumerate() {
+        if group_id as usize == groups.len() {
+            groups.push(Vec::new());
+        }
+        groups[group_id as usize].push(index as i64);
+    }
+    Ok(Value::List(Rc::new(
+        groups.into_iter().map(Value::from).collect(),
+    )))
+}
+
+/// Return 1 where `value` is zero, 0 otherwise.
+fn monadic_not(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
     value
-        .enlist()
-        .unwrap_or_else(|_| runtime.rank_error("monadic_enlist"))
+        .apply_dyadic_verb(&0.into(), |a, b| (a == b) as i64, |a, b| (a == b) as i64)
+        .map_err(|_| KError::domain())
+}
+
+/// Repeat each index of `value` as many times as the element at that index.
+fn monadic_where(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    let vector = match value {
+        Value::Vector(vector) => vector,
+        _ if value.is_atom() => return Err(KError::rank()),
+        _ => return Err(KError::domain()),
+    };
+
+    if vector.iter().any(|count| *count < 0) {
+        return Err(KError::domain());
+    }
+
+    Ok(vector
+        .iter()
+        .enumerate()
+        .flat_map(|(index, count)| std::iter::repeat_n(index as i64, *count as usize))
+        .collect::<Vec<_>>()
+        .into())
 }
 
 /// Reverse `value`.
-fn monadic_reverse(runtime: &Runtime, value: Value) -> Value {
-    value
-        .reverse()
-        .unwrap_or_else(|_| runtime.rank_error("monadic_reverse"))
+fn monadic_reverse(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    value.reverse().map_err(|_| KError::rank())
 }
 
-/// Return the first element of `value`.
-fn monadic_first(runtime: &Runtime, value: Value) -> Value {
-    dyadic_index_at(runtime, value, 0_i64.into())
+/// Return the indices that stably sort `value`.
+fn grade(value: &Value, descending: bool) -> Result<Value, KError> {
+    let order = |ordering: Ordering| {
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+
+    let mut indices = (0..value.len().unwrap_or(0)).collect::<Vec<_>>();
+    match value {
+        Value::Vector(vector) => {
+            indices.sort_by(|a, b| order(vector[*a].cmp(&vector[*b])));
+        }
+        Value::FloatVector(vector) => {
+            indices.sort_by(|a, b| order(vector[*a].total_cmp(&vector[*b])));
+        }
+        Value::CharVector(vector) => {
+            indices.sort_by(|a, b| order(vector[*a].cmp(&vector[*b])));
+        }
+        Value::SymbolVector(vector) => {
+            indices.sort_by(|a, b| order(vector[*a].cmp(&vector[*b])));
+        }
+        Value::List(_) | Value::Table(_) => return Err(KError::domain()),
+        Value::Dict(dict) => {
+            return index_at(dict.keys(), &grade(dict.values(), descending)?);
+        }
+        _ => return Err(KError::rank()),
+    }
+    Ok(indices
+        .into_iter()
+        .map(|index| index as i64)
+        .collect::<Vec<_>>()
+        .into())
+}
+
+/// Return the indices that sort `value` in ascending order, or the keys of a
+/// dictionary sorted by value.
+fn monadic_grade_up(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    grade(&value, false)
+}
+
+/// Return the indices that sort `value` in descending order, or the keys of a
+/// dictionary sorted by value.
+fn monadic_grade_down(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    grade(&value, true)
+}
+
+/// Return the reciprocal of `value`.
+fn monadic_reciprocal(runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    dyadic_divide(runtime, 1.0.into(), value)
+}
+
+/// Return the values of a dictionary.
+fn monadic_value(_runtime: &Runtime, value: Value) -> Result<Value, KError> {
+    match value {
+        Value::Dict(dict) => Ok(dict.values().clone()),
+        _ => Err(KError::domain()),
+    }
 }
 
 /// Add `left` and `right`.
-fn dyadic_add(runtime: &Runtime, left: Value, right: Value) -> Value {
-    left.apply_dyadic_verb(&right, i64::wrapping_add)
-        .unwrap_or_else(|_| runtime.domain_error("dyadic_add"))
+fn dyadic_add(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_dyadic_verb(&right, i64::wrapping_add, |a, b| a + b)
+        .map_err(|_| KError::domain())
 }
 
 /// Subtract `right` from `left`.
-fn dyadic_subtract(runtime: &Runtime, left: Value, right: Value) -> Value {
-    dyadic_add(runtime, left, -right)
+fn dyadic_subtract(runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    let right = right.negate().map_err(|_| KError::domain())?;
+    dyadic_add(runtime, left, right)
 }
 
 /// Modulo `left` and `right`.
-fn dyadic_modulo(runtime: &Runtime, left: Value, right: Value) -> Value {
-    if left.is_error() || right.is_error() {
-        return Value::Error;
+fn dyadic_modulo(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    match left {
+        Value::Atom(integer) if integer != 0 => {}
+        Value::Float(float) if float != 0.0 => {}
+        _ => return Err(KError::domain()),
     }
 
-    let modulus = match left {
-        Value::Atom(integer) if integer != 0 => integer,
-        _ => return runtime.domain_error("dyadic_modulo"),
+    right
+        .apply_dyadic_verb(&left, i64::wrapping_rem, |a, b| a % b)
+        .map_err(|_| KError::domain())
+}
+
+/// Make a dictionary from keys `left` and values `right`; an atom value is
+/// extended to every key. A numeric atom on the left is modulo instead.
+fn dyadic_dict(runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    let (keys, values) = match left {
+        Value::Atom(_) | Value::Float(_) => return dyadic_modulo(runtime, left, right),
+        _ if left.is_atom() => (left.enlist(), right.enlist()),
+        _ => {
+            let length = left.len().unwrap_or(0);
+            let values = match right.len() {
+                None => take(length, right),
+                Some(count) if count == length => right,
+                Some(_) => return Err(KError::length()),
+            };
+            (left, values)
+        }
     };
+    Ok(Value::Dict(Rc::new(Dict::new(keys, values))))
+}
 
-    match right {
-        Value::Atom(integer) => (integer % modulus).into(),
-        Value::Vector(vector) => vector
-            .iter()
-            .map(|integer| integer % modulus)
-            .collect::<Vec<_>>()
-            .into(),
-        Value::Error => Value::Error,
+/// Repeat `vector` cyclically up to `count` elements, or `fill` if it is empty.
+fn take_cyclic<T: Clone>(vector: &[T], count: usize, fill: T) -> Vec<T> {
+    if vector.is_empty() {
+        return vec![fill; count];
     }
+    (0..count)
+        .map(|index| vector[index % vector.len()].clone())
+        .collect()
 }
 
 /// Take the first `count` elements from `right`.
-fn dyadic_take(runtime: &Runtime, left: Value, right: Value) -> Value {
-    if left.is_error() || right.is_error() {
-        return Value::Error;
+fn dyadic_take(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    match left {
+        Value::Atom(integer) if integer >= 0 => Ok(take(integer as usize, right)),
+        _ if left.is_atom() => Err(KError::domain()),
+        _ => Err(KError::rank()),
     }
+}
 
-    let count = match left {
-        Value::Atom(integer) if integer >= 0 => integer as usize,
-        Value::Atom(_) => return runtime.domain_error("dyadic_take"),
-        _ => return runtime.rank_error("dyadic_take"),
-    };
-
-    match right {
-        Value::Atom(integer) => (0..count).map(|_| integer).collect::<Vec<_>>().into(),
-        Value::Vector(vector) => {
-            let length = vector.len();
-            (0..count)
-                .map(|index| vector[index.checked_rem(length).unwrap_or(0)])
-                .collect::<Vec<_>>()
-                .into()
+/// Take `count` elements from `value`, repeating an atom; a dictionary takes
+/// its first `count` entries.
+fn take(count: usize, value: Value) -> Value {
+    match value {
+        Value::Atom(integer) => vec![integer; count].into(),
+        Value::Float(float) => vec![float; count].into(),
+        Value::Vector(vector) => take_cyclic(&vector, count, 0).into(),
+        Value::Char(char) => Value::CharVector(Rc::new(vec![char; count])),
+        Value::Symbol(symbol) => vec![symbol; count].into(),
+        Value::Lambda(_) | Value::Verb(_) => Value::from_items(vec![value; count]),
+        Value::FloatVector(vector) => take_cyclic(&vector, count, 0.0).into(),
+        Value::CharVector(vector) => Value::CharVector(Rc::new(take_cyclic(&vector, count, b' '))),
+        Value::SymbolVector(vector) => take_cyclic(&vector, count, Rc::from("")).into(),
+        Value::List(list) => {
+            Value::from_items(take_cyclic(&list, count, Value::List(Rc::new(Vec::new()))))
+        }
+        Value::Dict(dict) => Value::Dict(Rc::new(Dict::new(
+            take(count, dict.keys().clone()),
+            take(count, dict.values().clone()),
+        ))),
+        Value::Table(table) => {
+            let Ok(table) =
+                table.map_columns(|column| Ok::<_, Infallible>(take(count, column.clone())));
+            Value::Table(Rc::new(table))
         }
-        Value::Error => Value::Error,
     }
 }
 
 /// Concatenate `left` and `right`.
-fn dyadic_concatenate(runtime: &Runtime, left: Value, right: Value) -> Value {
-    match (left, right) {
-        (Value::Error, _) | (_, Value::Error) => Value::Error,
-        (a @ Value::Atom(_), b @ _) => dyadic_concatenate(runtime, a.enlist().unwrap(), b),
-        (a @ _, b @ Value::Atom(_)) => dyadic_concatenate(runtime, a, b.enlist().unwrap()),
-        (Value::Vector(left_vector), Value::Vector(right_vector)) => left_vector
-            .iter()
-            .chain(right_vector.iter())
-            .cloned()
-            .collect::<Vec<_>>()
-            .into(),
-    }
+fn dyadic_concatenate(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    Ok(left.concatenate(&right))
 }
 
 /// Return the element at `index` in `left`.
-fn dyadic_index_at(runtime: &Runtime, left: Value, right: Value) -> Value {
-    if left.is_error() || right.is_error() {
-        return Value::Error;
-    }
+fn dyadic_index_at(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    index_at(&left, &right)
+}
 
-    let left_vector = match left {
-        Value::Vector(vector) => vector,
-        Value::Atom(_) => return runtime.rank_error("dyadic_index_at"),
-        Value::Error => return Value::Error,
-    };
+/// Index `left` at `right`; a list of indices indexes item by item. A
+/// dictionary is indexed by key.
+fn index_at(left: &Value, right: &Value) -> Result<Value, KError> {
+    if let Value::Dict(dict) = left {
+        if right.is_atom() || dict.position(right).is_some() {
+            return Ok(dict.get(right));
+        }
+        return Ok(Value::from_items(
+            right
+                .items()
+                .iter()
+                .map(|key| index_at(left, key))
+                .collect::<Result<Vec<_>, _>>()?,
+        ));
+    }
+    // Symbols index a table's columns and vectors of indices select rows.
+    if let Value::Table(table) = left {
+        match right {
+            Value::Symbol(_) | Value::SymbolVector(_) => {
+                return index_at(&Value::Dict(table.clone()), right);
+            }
+            Value::Vector(_) | Value::List(_) => {
+                let rows = table.map_columns(|column| index_at(column, right))?;
+                return Ok(Value::Table(Rc::new(rows)));
+            }
+            _ => {}
+        }
+    }
 
-    let left_length = left_vector.len();
+    let left_length = left.len().ok_or_else(KError::rank)?;
+    let fill = left.null_item();
 
     match right {
         Value::Atom(index_integer) => {
+            let index_integer = *index_integer;
             let index = index_integer as usize;
 
             if index_integer < 0 || index > left_length {
-                return runtime.length_error("dyadic_index_at");
+                return Err(KError::length());
             }
 
-            left_vector.get(index).copied().unwrap_or(0).into()
+            Ok(left.item(index).unwrap_or(fill))
         }
-        Value::Vector(indices) => indices
-            .iter()
-            .map(|index| {
-                left_vector
-                    .get(*index.max(&0) as usize)
-                    .copied()
-                    .unwrap_or(0)
-            })
-            .collect::<Vec<_>>()
-            .into(),
+        Value::Vector(indices) => Ok(Value::from_items(
+            indices
+                .iter()
+                .map(|index| {
+                    left.item(*index.max(&0) as usize)
+                        .unwrap_or_else(|| fill.clone())
+                })
+                .collect(),
+        )),
+        Value::List(indices) => Ok(Value::from_items(
+            indices
+                .iter()
+                .map(|index| index_at(left, index))
+                .collect::<Result<Vec<_>, _>>()?,
+        )),
+        _ => Err(KError::domain()),
+    }
+}
+
+/// Index `left` at depth: each item of `right` indexes one level deeper.
+fn dyadic_index_depth(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    index_path(left, &right.items())
+}
 
-        Value::Error => Value::Error,
+/// Index `value` at depth, one level per index in `path`, as in `m[i;j]`.
+/// An index that selects several items applies the rest of the path to each
+/// of them, so `m[0 1;1]` takes the second item of the first two rows.
+pub(crate) fn index_path(value: Value, path: &[Value]) -> Result<Value, KError> {
+    let Some((index, rest)) = path.split_first() else {
+        return Ok(value);
+    };
+    let selected = index_at(&value, index)?;
+    let is_key = matches!(&value, Value::Dict(dict) if dict.position(index).is_some());
+    if rest.is_empty() || index.is_atom() || is_key {
+        return index_path(selected, rest);
     }
+    let items = selected
+        .items()
+        .into_iter()
+        .map(|item| index_path(item, rest))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from_items(items))
 }
 
 /// Return 1 if `left` is equal to `right`, 0 otherwise.
-fn dyadic_equal(runtime: &Runtime, left: Value, right: Value) -> Value {
-    left.apply_dyadic_verb(&right, |a, b| if a == b { 1 } else { 0 })
-        .unwrap_or_else(|_| runtime.domain_error("dyadic_equal"))
+fn dyadic_equal(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_comparison(&right, |ordering| ordering == Ordering::Equal)
+        .map_err(|_| KError::domain())
 }
 
 /// Return 1 if `left` is not equal to `right`, 0 otherwise.
-fn dyadic_not_equal(runtime: &Runtime, left: Value, right: Value) -> Value {
-    left.apply_dyadic_verb(&right, |a, b| if a != b { 1 } else { 0 })
-        .unwrap_or_else(|_| runtime.domain_error("dyadic_not_equal"))
+fn dyadic_not_equal(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_comparison(&right, |ordering| ordering != Ordering::Equal)
+        .map_err(|_| KError::domain())
 }
 
-/// Return the logical AND of `left` and `right`.
-fn dyadic_and(runtime: &Runtime, left: Value, right: Value) -> Value {
-    left.apply_dyadic_verb(&right, |a, b| a & b)
-        .unwrap_or_else(|_| runtime.domain_error("dyadic_and"))
+/// Return the minimum of `left` and `right`; the logical AND on booleans.
+fn dyadic_min(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_dyadic_verb(&right, i64::min, f64::min)
+        .map_err(|_| KError::domain())
 }
 
-/// Return the logical OR of `left` and `right`.
-fn dyadic_or(runtime: &Runtime, left: Value, right: Value) -> Value {
-    left.apply_dyadic_verb(&right, |a, b| a | b)
-        .unwrap_or_else(|_| runtime.domain_error("dyadic_or"))
+/// Return the maximum of `left` and `right`; the logical OR on booleans.
+fn dyadic_max(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_dyadic_verb(&right, i64::max, f64::max)
+        .map_err(|_| KError::domain())
 }
 
 /// Return the product of `left` and `right`.
-fn dyadic_product(runtime: &Runtime, left: Value, right: Value) -> Value {
-    left.apply_dyadic_verb(&right, |a, b| a.wrapping_mul(b))
-        .unwrap_or_else(|_| runtime.domain_error("dyadic_product"))
+fn dyadic_product(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_dyadic_verb(&right, i64::wrapping_mul, |a, b| a * b)
+        .map_err(|_| KError::domain())
 }
 
-/// Apply `verb` to `value` over the vector.
-fn adverb_over(runtime: &Runtime, verb_index: usize, value: Value) -> Value {
-    match value {
-        Value::Atom(_) => value,
-        Value::Vector(vector) => vector.iter().fold(0.into(), |result, integer| {
-            apply_dyadic_verb(runtime, verb_index, result, integer.into())
-        }),
-        Value::Error => Value::Error,
+/// Return 1 if `left` is less than `right`, 0 otherwise.
+fn dyadic_less(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_comparison(&right, |ordering| ordering == Ordering::Less)
+        .map_err(|_| KError::domain())
+}
+
+/// Return 1 if `left` is greater than `right`, 0 otherwise.
+fn dyadic_more(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    left.apply_comparison(&right, |ordering| ordering == Ordering::Greater)
+        .map_err(|_| KError::domain())
+}
+
+/// Divide `left` by `right`. Integers divide rounding toward negative
+/// infinity; if either side is a float the division is exact.
+fn dyadic_divide(_runtime: &Runtime, left: Value, right: Value) -> Result<Value, KError> {
+    fn has_zero(value: &Value) -> bool {
+        match value {
+            Value::Atom(integer) => *integer == 0,
+            Value::Float(float) => *float == 0.0,
+            Value::Vector(vector) => vector.contains(&0),
+            Value::FloatVector(vector) => vector.contains(&0.0),
+            Value::List(list) => list.iter().any(has_zero),
+            Value::Dict(dict) => has_zero(dict.values()),
+            _ => false,
+        }
     }
+
+    if has_zero(&right) {
+        return Err(KError::domain());
+    }
+
+    left.apply_dyadic_verb(
+        &right,
+        |a, b| {
+            let quotient = a.wrapping_div(b);
+            if a.wrapping_rem(b) != 0 && (a < 0) != (b < 0) {
+                quotient - 1
+            } else {
+                quotient
+            }
+        },
+        |a, b| a / b,
+    )
+    .map_err(|_| KError::domain())
 }
 
-/// Apply `verb` to `value` while scanning the vector.
-fn adverb_scan(runtime: &Runtime, verb_index: usize, value: Value) -> Value {
-    match value {
-        Value::Atom(_) => value,
-        Value::Vector(vector) => {
-            let mut iter = vector.iter();
-            let Some(first) = iter.next() else {
-                return vec![].into();
-            };
+/// The result of folding an empty list with a built-in verb: its identity,
+/// such as 0 for `+` and 1 for `*`.
+fn identity(verb: &Verb) -> Option<Value> {
+    let Verb::Builtin(verb_index) = verb else {
+        return None;
+    };
+    match VERB_TOKENS.as_bytes().get(*verb_index)? {
+        b'+' | b'-' => Some(0.into()),
+        b'*' => Some(1.into()),
+        b'&' => Some(i64::MAX.into()),
+        b'|' => Some(i64::MIN.into()),
+        _ => None,
+    }
+}
 
-            let mut result = Value::Atom(*first);
-            let mut output = Vec::with_capacity(vector.len());
-            output.push(*first);
+/// Fold `value` with `verb`, keeping every step if `scan` is set.
+///
+/// A dyadic verb folds from `left` if given, otherwise from the first item.
+/// A monadic verb is applied repeatedly instead: `left` times for an integer
+/// `left`, while the lambda `left` returns nonzero, or with no `left` until
+/// the result stops changing or returns to `value`.
+fn fold(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    value: Value,
+    scan: bool,
+) -> Result<Value, KError> {
+    let adverb = if scan { "\\" } else { "/" };
+    let mut results = Vec::new();
+    let keep = |result: &Value, results: &mut Vec<Value>| {
+        if !scan {
+            results.clear();
+        }
+        results.push(result.clone());
+    };
 
-            for integer in iter {
-                match apply_dyadic_verb(runtime, verb_index, result.clone(), (*integer).into()) {
-                    Value::Atom(value) => {
-                        result = Value::Atom(value);
-                        output.push(value);
-                    }
-                    _ => {
-                        result = Value::Error;
-                        output.push(0);
+    if verb.arity() == 1 {
+        let mut current = value.clone();
+        let mut steps = 0;
+        keep(&current, &mut results);
+        loop {
+            let more = match &left {
+                None => true,
+                Some(Value::Atom(count)) => steps < *count,
+                Some(condition @ (Value::Lambda(_) | Value::Verb(_))) => {
+                    match call(runtime, condition.clone(), vec![current.clone()])? {
+                        Value::Atom(truth) => truth != 0,
+                        _ => return Err(KError::domain().with_verb(adverb)),
                     }
                 }
+                Some(_) => return Err(KError::domain().with_verb(adverb)),
+            };
+            if !more {
+                break;
             }
+            let next = verb.apply_monadic(runtime, current.clone())?;
+            if left.is_none() && (next == current || next == value) {
+                break;
+            }
+            keep(&next, &mut results);
+            current = next;
+            steps += 1;
+        }
+    } else {
+        let mut items = value.items().into_iter();
+        let seed = match left {
+            Some(seed) => seed,
+            None => match items.next() {
+                Some(first) => {
+                    keep(&first, &mut results);
+                    first
+                }
+                None if scan => return Ok(value),
+                None => return Ok(identity(verb).unwrap_or(value)),
+            },
+        };
+        let mut current = seed;
+        for item in items {
+            current = verb.apply_dyadic(runtime, current, item)?;
+            keep(&current, &mut results);
+        }
+        if results.is_empty() {
+            return Ok(current);
+        }
+    }
+
+    match scan {
+        true => Ok(Value::from_items(results)),
+        false => Ok(results.pop().unwrap()),
+    }
+}
+
+/// Apply `verb` over `value`, returning the final result.
+fn adverb_over(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    value: Value,
+) -> Result<Value, KError> {
+    fold(runtime, verb, left, value, false)
+}
 
-            output.into()
+/// Apply `verb` over `value`, returning every intermediate result.
+fn adverb_scan(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    value: Value,
+) -> Result<Value, KError> {
+    fold(runtime, verb, left, value, true)
+}
+
+/// Apply `verb` to each item of `value`, or to corresponding items of
+/// `left` and `value`, extending an atom against the other side. Applied
+/// monadically to a dictionary, it maps the values and keeps the keys.
+fn adverb_each(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    value: Value,
+) -> Result<Value, KError> {
+    let Some(left) = left else {
+        if let Value::Dict(dict) = &value {
+            let values = adverb_each(runtime, verb, None, dict.values().clone())?;
+            return Ok(Value::Dict(Rc::new(Dict::new(dict.keys().clone(), values))));
         }
-        Value::Error => Value::Error,
+        let Some(length) = value.len() else {
+            return verb.apply_monadic(runtime, value);
+        };
+        let items = (0..length)
+            .map(|index| verb.apply_monadic(runtime, value.item(index).unwrap()))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(Value::from_items(items));
+    };
+
+    let length = match (left.len(), value.len()) {
+        (Some(a), Some(b)) if a != b => return Err(KError::length().with_verb("'")),
+        (Some(length), _) | (None, Some(length)) => length,
+        (None, None) => return verb.apply_dyadic(runtime, left, value),
+    };
+    let items = (0..length)
+        .map(|index| {
+            let a = left.item(index).unwrap_or_else(|| left.clone());
+            let b = value.item(index).unwrap_or_else(|| value.clone());
+            verb.apply_dyadic(runtime, a, b)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from_items(items))
+}
+
+/// Apply `verb` to each item of `left` and the whole of `right`.
+fn adverb_each_left(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    right: Value,
+) -> Result<Value, KError> {
+    let left = left.ok_or_else(|| KError::rank().with_verb("\\:"))?;
+    if left.is_atom() {
+        return verb.apply_dyadic(runtime, left, right);
+    }
+    let items = left
+        .items()
+        .into_iter()
+        .map(|item| verb.apply_dyadic(runtime, item, right.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from_items(items))
+}
+
+/// Apply `verb` to the whole of `left` and each item of `right`.
+fn adverb_each_right(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    right: Value,
+) -> Result<Value, KError> {
+    let left = left.ok_or_else(|| KError::rank().with_verb("/:"))?;
+    if right.is_atom() {
+        return verb.apply_dyadic(runtime, left, right);
     }
+    let items = right
+        .items()
+        .into_iter()
+        .map(|item| verb.apply_dyadic(runtime, left.clone(), item))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from_items(items))
 }
 
-const MONADIC_VERBS: [MonadicVerb; 12] = [
+/// Apply `verb` to each item of `value` and the item before it, as in
+/// `-':` for deltas. The first item is paired with `left`, or kept as it is
+/// without one.
+fn adverb_each_prior(
+    runtime: &mut Runtime,
+    verb: &Verb,
+    left: Option<Value>,
+    value: Value,
+) -> Result<Value, KError> {
+    let Some(length) = value.len() else {
+        return Err(KError::rank().with_verb("':"));
+    };
+
+    let mut items = Vec::with_capacity(length);
+    let mut prior = left;
+    for index in 0..length {
+        let item = value.item(index).unwrap();
+        items.push(match prior {
+            Some(prior) => verb.apply_dyadic(runtime, item.clone(), prior)?,
+            None => item.clone(),
+        });
+        prior = Some(item);
+    }
+    Ok(Value::from_items(items))
+}
+
+const MONADIC_VERBS: [MonadicVerb; 16] = [
     monadic_not_a_verb,
-    monadic_not_implemented,
+    monadic_flip,
     monadic_negate,
     monadic_enumerate,
     monadic_count,
     monadic_enlist,
     monadic_first,
-    monadic_not_implemented,
-    monadic_not_implemented,
-    monadic_not_implemented,
+    monadic_group,
+    monadic_not,
+    monadic_where,
     monadic_reverse,
-    monadic_not_implemented,
+    monadic_first,
+    monadic_grade_up,
+    monadic_grade_down,
+    monadic_reciprocal,
+    monadic_value,
 ];
 
-const DYADIC_VERBS: [DyadicVerb; 12] = [
+const DYADIC_VERBS: [DyadicVerb; 16] = [
     dyadic_not_a_verb,
     dyadic_add,
     dyadic_subtract,
-    dyadic_modulo,
+    dyadic_dict,
     dyadic_take,
     dyadic_concatenate,
     dyadic_index_at,
     dyadic_equal,
     dyadic_not_equal,
-    dyadic_and,
-    dyadic_or,
+    dyadic_min,
+    dyadic_max,
     dyadic_product,
+    dyadic_less,
+    dyadic_more,
+    dyadic_divide,
+    dyadic_index_depth,
 ];
 
-const ADVERBS: [Adverb; 3] = [|_runtime, _, value| value, adverb_over, adverb_scan];
+const ADVERBS: [Adverb; 7] = [
+    |_runtime, _, _, value| Ok(value),
+    adverb_over,
+    adverb_scan,
+    adverb_each,
+    adverb_each_right,
+    adverb_each_left,
+    adverb_each_prior,
+];
 
 /// Helper function to apply a monadic verb.
-pub(crate) fn apply_monadic_verb(runtime: &Runtime, verb_index: usize, value: Value) -> Value {
+pub(crate) fn apply_monadic_verb(
+    runtime: &Runtime,
+    verb_index: usize,
+    value: Value,
+) -> Result<Value, KError> {
     let verb = MONADIC_VERBS
         .get(verb_index)
         .copied()
         .unwrap_or(monadic_not_a_verb);
-    verb(runtime, value)
+    verb(runtime, value).map_err(|error| error.with_verb(verb_token(verb_index)))
 }
 
 /// Helper function to apply a dyadic verb.
@@ -396,24 +1233,25 @@ pub(crate) fn apply_dyadic_verb(
     verb_index: usize,
     left: Value,
     right: Value,
-) -> Value {
+) -> Result<Value, KError> {
     let verb = DYADIC_VERBS
         .get(verb_index)
         .copied()
         .unwrap_or(dyadic_not_a_verb);
-    verb(runtime, left, right)
+    verb(runtime, left, right).map_err(|error| error.with_verb(verb_token(verb_index)))
 }
 
 /// Helper function to apply an adverb.
 pub(crate) fn apply_adverb(
-    runtime: &Runtime,
+    runtime: &mut Runtime,
     adverb_index: usize,
-    verb_index: usize,
+    verb: &Verb,
+    left: Option<Value>,
     value: Value,
-) -> Value {
+) -> Result<Value, KError> {
     let adverb = ADVERBS
         .get(adverb_index)
         .copied()
-        .unwrap_or(|_, _, value| value);
-    adverb(runtime, verb_index, value)
+        .unwrap_or(|_, _, _, value| Ok(value));
+    adverb(runtime, verb, left, value)
 }
@@ -1,27 +1,74 @@
-pub(crate) const VERB_TOKENS: &str = " +-!#,@=~&|*";
-pub(crate) const ADVERB_TOKENS: &str = " /\\";
+use crate::error::{KError, Span};
+use crate::value::{Lambda, Value};
+use std::rc::Rc;
+
+pub(crate) const VERB_TOKENS: &str = " +-!#,@=~&|*<>%.";
+pub(crate) const ADVERB_TOKENS: [&str; 7] = [" ", "/", "\\", "'", "/:", "\\:", "':"];
 
 /// A token in the k/simple programming language.
 #[derive(Clone, Debug)]
 pub(crate) enum Token {
-    /// A number.
-    Number(i64),
-    /// A global variable, a-z.
-    Global(u8),
-    /// A symbol, verb or adverb.
+    /// A literal: a number, a run of space-separated numbers, a string or a
+    /// lambda.
+    Literal(Value),
+    /// A symbol literal, `` `a``, or a run of them, `` `a`b`c``.
+    Symbols(Vec<String>),
+    /// A variable name: a letter followed by letters and digits, such as
+    /// `a` or `qty2`.
+    Name(Rc<str>),
+    /// A symbol or verb.
     Symbol(u8),
+    /// An adverb, by its index in `ADVERB_TOKENS`.
+    Adverb(usize),
     /// A colon.
     Colon,
+    /// An opening parenthesis.
+    LeftParen,
+    /// A closing parenthesis.
+    RightParen,
+    /// An opening bracket, starting the arguments of a function call.
+    LeftBracket,
+    /// A closing bracket.
+    RightBracket,
+    /// A semicolon, separating list items.
+    Semicolon,
+    /// A control form, `$`, `do` or `while`, followed by bracketed
+    /// arguments.
+    Control(Control),
+}
+
+/// A control form. Its arguments are evaluated only when needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Control {
+    /// `$[c;t;f]` evaluates `t` if `c` is nonzero and `f` otherwise.
+    Cond,
+    /// `do[n;e1;e2;...]` evaluates the expressions `n` times.
+    Do,
+    /// `while[c;e1;e2;...]` evaluates the expressions while `c` is nonzero.
+    While,
+}
+
+impl Control {
+    /// Return the control form named by a reserved word.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "do" => Some(Self::Do),
+            "while" => Some(Self::While),
+            _ => None,
+        }
+    }
 }
 
 impl Token {
     /// Returns true if the token can start a negative number.
     pub(crate) fn can_start_negative(&self) -> bool {
         match self {
-            Token::Colon => true,
-            Token::Symbol(symbol) => {
-                VERB_TOKENS.as_bytes().contains(symbol) || ADVERB_TOKENS.as_bytes().contains(symbol)
-            }
+            Token::Colon
+            | Token::LeftParen
+            | Token::LeftBracket
+            | Token::Semicolon
+            | Token::Adverb(_) => true,
+            Token::Symbol(symbol) => VERB_TOKENS.as_bytes().contains(symbol),
             _ => false,
         }
     }
@@ -35,22 +82,68 @@ pub(crate) fn verb_index(token: u8) -> usize {
         .unwrap_or(0)
 }
 
-pub(crate) fn adverb_index(token: u8) -> usize {
-    ADVERB_TOKENS
-        .as_bytes()
-        .iter()
-        .position(|&byte| byte == token)
+/// The k glyph of the verb at `index` in `VERB_TOKENS`.
+pub(crate) fn verb_token(index: usize) -> &'static str {
+    VERB_TOKENS.get(index..=index).unwrap_or(" ")
+}
+
+/// The k glyph of the adverb at `index` in `ADVERB_TOKENS`.
+pub(crate) fn adverb_token(index: usize) -> &'static str {
+    ADVERB_TOKENS.get(index).copied().unwrap_or(" ")
+}
+
+/// Return the index of the adverb at the start of `bytes`, preferring the
+/// two-character adverbs such as `/:`, or 0 if there is none.
+fn adverb_index(bytes: &[u8]) -> usize {
+    (1..ADVERB_TOKENS.len())
+        .filter(|index| bytes.starts_with(ADVERB_TOKENS[*index].as_bytes()))
+        .max_by_key(|index| ADVERB_TOKENS[*index].len())
         .unwrap_or(0)
 }
 
-pub(crate) fn tokenize_line(line: &str) -> Result<Vec<Token>, ()> {
+/// Split a line into tokens, each with the span of source it was read from.
+pub(crate) fn tokenize_line(line: &str) -> Result<Vec<(Token, Span)>, KError> {
+    tokenize(line, 0)
+}
+
+/// Tokenize `line` from `start` on. Spans count from the start of `line`, so
+/// that those of a lambda body point into the line the lambda is written on.
+pub(crate) fn tokenize(line: &str, start: usize) -> Result<Vec<(Token, Span)>, KError> {
     let bytes = line.as_bytes();
     let mut tokens = Vec::new();
-    let mut index = 0;
+    let mut spans = Vec::new();
+    let mut index = start;
+    let mut token_start = start;
 
     while index < bytes.len() {
+        // Each pass reads at most one token, so a token read by the last
+        // pass ends where this one starts.
+        if spans.len() < tokens.len() {
+            spans.push(Span::new(token_start, index));
+        }
+        token_start = index;
         let byte = bytes[index];
 
+        // A line break separates statements, like `;`, unless it follows an
+        // opening bracket or comes before a closing one.
+        if byte == b'\n' {
+            let follows_opening = tokens.last().is_none_or(|token| {
+                matches!(
+                    token,
+                    Token::Semicolon | Token::LeftParen | Token::LeftBracket
+                )
+            });
+            let precedes_closing = bytes[index..]
+                .iter()
+                .find(|byte| !byte.is_ascii_whitespace())
+                .is_none_or(|byte| matches!(byte, b')' | b']' | b'}'));
+            if !follows_opening && !precedes_closing {
+                tokens.push(Token::Semicolon);
+            }
+            index += 1;
+            continue;
+        }
+
         // Skip whitespace.
         if byte.is_ascii_whitespace() {
             index += 1;
@@ -64,54 +157,271 @@ pub(crate) fn tokenize_line(line: &str) -> Result<Vec<Token>, ()> {
             continue;
         }
 
-        // Check if the token can start a negative number.
-        let can_start_negative = tokens.last().map_or(true, |t| t.can_start_negative());
-
-        // Read number.
-        if (byte == b'-'
-            && can_start_negative
-            && index + 1 < bytes.len()
-            && bytes[index + 1].is_ascii_digit())
-            || byte.is_ascii_digit()
-        {
-            let mut sign: i64 = 1;
-            if byte == b'-' {
-                sign = -1;
-                index += 1;
-            }
+        // List item separator.
+        if byte == b';' {
+            tokens.push(Token::Semicolon);
+            index += 1;
+            continue;
+        }
 
-            let mut value: i64 = 0;
-            let mut saw_digit = false;
+        // Grouping.
+        if byte == b'(' || byte == b')' {
+            tokens.push(if byte == b'(' {
+                Token::LeftParen
+            } else {
+                Token::RightParen
+            });
+            index += 1;
+            continue;
+        }
 
-            while index < bytes.len() && bytes[index].is_ascii_digit() {
-                saw_digit = true;
-                let digit = (bytes[index] - b'0') as i64;
-                value = value
-                    .checked_mul(10)
-                    .and_then(|value| value.checked_add(digit))
-                    .ok_or(())?;
-                index += 1;
+        // Arguments of a function call.
+        if byte == b'[' || byte == b']' {
+            tokens.push(if byte == b'[' {
+                Token::LeftBracket
+            } else {
+                Token::RightBracket
+            });
+            index += 1;
+            continue;
+        }
+
+        // Lambda. The body is tokenized on its own.
+        if byte == b'{' {
+            let end = find_closing_brace(bytes, index)?;
+            let body = tokenize(&line[..end], index + 1)?;
+            let lambda = Lambda::new(line[index..=end].to_string(), &body)?;
+            tokens.push(Token::Literal(Value::Lambda(Rc::new(lambda))));
+            index = end + 1;
+            continue;
+        }
+
+        // String literal. A single character is a char atom.
+        if byte == b'"' {
+            let (chars, end) = read_string(bytes, index)?;
+            tokens.push(Token::Literal(match chars.as_slice() {
+                [char] => Value::Char(*char),
+                _ => Value::CharVector(Rc::new(chars)),
+            }));
+            index = end;
+            continue;
+        }
+
+        // Symbol literal. Adjacent symbols form a symbol vector.
+        if byte == b'`' {
+            let mut names = Vec::new();
+            while bytes.get(index) == Some(&b'`') {
+                let start = index + 1;
+                index = start
+                    + bytes[start..]
+                        .iter()
+                        .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'.')
+                        .count();
+                names.push(line[start..index].to_string());
             }
+            tokens.push(Token::Symbols(names));
+            continue;
+        }
 
-            if !saw_digit {
-                return Err(());
+        // Check if the token can start a negative number.
+        let can_start_negative = tokens.last().is_none_or(|t| t.can_start_negative());
+
+        // Read a number, or a vector literal: a run of space-separated numbers.
+        // A minus preceded by a space continues the run, so `1 -2` is a vector.
+        if starts_number(bytes, index, can_start_negative) {
+            let mut numbers = Vec::new();
+            loop {
+                let (number, end) = read_number(line, index)?;
+                numbers.push(number);
+                index = end;
+
+                let next = index
+                    + bytes[index..]
+                        .iter()
+                        .take_while(|byte| byte.is_ascii_whitespace() && **byte != b'\n')
+                        .count();
+                if next == index || !starts_number(bytes, next, true) {
+                    break;
+                }
+                index = next;
             }
 
-            tokens.push(Token::Number(value * sign));
+            let literal = match numbers.len() {
+                1 => numbers.pop().unwrap(),
+                _ => Value::from_items(numbers),
+            };
+            tokens.push(Token::Literal(literal));
+            continue;
+        }
+
+        // Read a name, or a reserved word.
+        if byte.is_ascii_alphabetic() {
+            let start = index;
+            index += bytes[index..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_alphanumeric())
+                .count();
+            let name = &line[start..index];
+            tokens.push(match Control::from_name(name) {
+                Some(control) => Token::Control(control),
+                None => Token::Name(Rc::from(name)),
+            });
             continue;
         }
 
-        // Read global variable.
-        if byte.is_ascii_lowercase() {
-            tokens.push(Token::Global(byte));
+        // Conditional.
+        if byte == b'$' {
+            tokens.push(Token::Control(Control::Cond));
             index += 1;
             continue;
         }
 
+        // Read an adverb.
+        let adverb = adverb_index(&bytes[index..]);
+        if adverb != 0 {
+            tokens.push(Token::Adverb(adverb));
+            index += ADVERB_TOKENS[adverb].len();
+            continue;
+        }
+
         // Read symbol.
         tokens.push(Token::Symbol(byte));
         index += 1;
     }
+    if spans.len() < tokens.len() {
+        spans.push(Span::new(token_start, index));
+    }
+
+    Ok(tokens.into_iter().zip(spans).collect())
+}
+
+/// Returns true if `source` opens more brackets than it closes, so that it
+/// continues on the next line.
+pub(crate) fn is_incomplete(source: &str) -> bool {
+    let bytes = source.as_bytes();
+    let mut depth = 0_i64;
+    let mut index = 0;
+    while let Some(&byte) = bytes.get(index) {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'"' => match read_string(bytes, index) {
+                Ok((_, end)) => {
+                    index = end;
+                    continue;
+                }
+                Err(_) => return false,
+            },
+            _ => {}
+        }
+        index += 1;
+    }
+    depth > 0
+}
+
+/// Find the `}` matching the `{` at `start`, skipping string literals.
+fn find_closing_brace(bytes: &[u8], start: usize) -> Result<usize, KError> {
+    let mut depth = 0;
+    let mut index = start;
+    while let Some(&byte) = bytes.get(index) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(index);
+                }
+            }
+            b'"' => {
+                index = read_string(bytes, index)?.1;
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    Err(KError::parse().with_span(Span::new(start, index)))
+}
+
+/// Returns true if a number starts at `index`.
+fn starts_number(bytes: &[u8], index: usize, can_start_negative: bool) -> bool {
+    match bytes.get(index) {
+        Some(byte) if byte.is_ascii_digit() => true,
+        Some(b'-') => can_start_negative && bytes.get(index + 1).is_some_and(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+/// Read an integer (`-12`) or a float (`1.5`, `1e3`, `-2.5e-3`) starting at
+/// `start`. Returns the number and the index just past it.
+fn read_number(line: &str, start: usize) -> Result<(Value, usize), KError> {
+    let bytes = line.as_bytes();
+    let digits = |index: usize| {
+        index
+            + bytes[index..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .count()
+    };
+
+    let mut index = start;
+    if bytes[index] == b'-' {
+        index += 1;
+    }
+    index = digits(index);
+
+    let mut is_float = false;
+    if bytes.get(index) == Some(&b'.') {
+        is_float = true;
+        index = digits(index + 1);
+    }
+    if matches!(bytes.get(index), Some(b'e' | b'E')) {
+        let mut exponent = index + 1;
+        if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+            exponent += 1;
+        }
+        if bytes.get(exponent).is_some_and(u8::is_ascii_digit) {
+            is_float = true;
+            index = digits(exponent);
+        }
+    }
+
+    let text = &line[start..index];
+    let error = || KError::parse().with_span(Span::new(start, index));
+    let number = if is_float {
+        Value::Float(text.parse().map_err(|_| error())?)
+    } else {
+        Value::Atom(text.parse().map_err(|_| error())?)
+    };
+    Ok((number, index))
+}
+
+/// Read a string literal starting at the opening quote at `start`. Returns
+/// the unescaped characters and the index just past the closing quote.
+fn read_string(bytes: &[u8], start: usize) -> Result<(Vec<u8>, usize), KError> {
+    let mut chars = Vec::new();
+    let mut index = start + 1;
+
+    while let Some(&byte) = bytes.get(index) {
+        match byte {
+            b'"' => return Ok((chars, index + 1)),
+            b'\\' => {
+                let escaped = *bytes
+                    .get(index + 1)
+                    .ok_or_else(|| KError::parse().with_span(Span::new(start, index + 1)))?;
+                chars.push(match escaped {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    _ => escaped,
+                });
+                index += 2;
+            }
+            _ => {
+                chars.push(byte);
+                index += 1;
+            }
+        }
+    }
 
-    Ok(tokens)
+    Err(KError::parse().with_span(Span::new(start, index)))
 }
@@ -1,92 +1,1000 @@
+use crate::compile::{Op, compile};
+use crate::error::{KError, Span};
+use crate::parse::{Node, parse};
+use crate::token::{Token, adverb_token, verb_token};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt::Display;
-use std::ops::Neg;
 use std::rc::Rc;
 
 /// A value in the k/simple programming language.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) enum Value {
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
     Atom(i64),
+    Float(f64),
+    Char(u8),
+    Symbol(Rc<str>),
     Vector(Rc<Vec<i64>>),
-    Error,
+    FloatVector(Rc<Vec<f64>>),
+    CharVector(Rc<Vec<u8>>),
+    SymbolVector(Rc<Vec<Rc<str>>>),
+    List(Rc<Vec<Value>>),
+    Dict(Rc<Dict>),
+    Table(Rc<Dict>),
+    Lambda(Rc<Lambda>),
+    Verb(Rc<Verb>),
+}
+
+/// A user-defined function, `{x+y}`. It takes as many arguments as the
+/// highest of the implicit arguments `x`, `y` and `z` it refers to.
+#[derive(Clone, Debug)]
+pub struct Lambda {
+    source: String,
+    body: Node,
+    code: Vec<Op>,
+    arity: usize,
+}
+
+impl Lambda {
+    /// Create a lambda from its source, braces included, and the tokens of
+    /// its body, which are parsed into a block.
+    pub(crate) fn new(source: String, body: &[(Token, Span)]) -> Result<Self, KError> {
+        let arity = body
+            .iter()
+            .filter_map(|(token, _)| match token {
+                Token::Name(name) => match name.as_ref() {
+                    "x" => Some(1),
+                    "y" => Some(2),
+                    "z" => Some(3),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let body = parse(body)?;
+        let code = compile(&body);
+        Ok(Self {
+            source,
+            body,
+            code,
+            arity,
+        })
+    }
+
+    /// The source of the lambda, braces included.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The number of arguments the lambda takes.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// The parsed body.
+    pub(crate) fn body(&self) -> &Node {
+        &self.body
+    }
+
+    /// The body compiled to bytecode.
+    pub(crate) fn code(&self) -> &[Op] {
+        &self.code
+    }
+}
+
+/// A verb used as a value: `+`, `+/`, `+[1]` or `*|`. Lambdas are values of
+/// their own, and become verbs when an adverb modifies them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verb {
+    /// A built-in verb, by its index in `VERB_TOKENS`. Called with two
+    /// arguments it applies dyadically, and with one it is projected.
+    Builtin(usize),
+    /// A built-in verb that applies monadically, as in the composition `*|`.
+    Monadic(usize),
+    /// A lambda.
+    Lambda(Rc<Lambda>),
+    /// A verb modified by an adverb, by its index in `ADVERB_TOKENS`: `+/`.
+    Derived(Rc<Verb>, usize),
+    /// A verb with its leading arguments fixed: `+[1]`.
+    Projection(Rc<Verb>, Vec<Value>),
+    /// Two verbs applied one after the other, the inner one first: `*|`.
+    Composition(Rc<Verb>, Rc<Verb>),
+}
+
+/// Lambdas are equal when their source is.
+impl PartialEq for Lambda {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+/// A dictionary: a vector of keys and a vector of values of the same length.
+/// A table is a dictionary of symbol keys and a list of equal-length columns.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dict {
+    keys: Value,
+    values: Value,
+}
+
+impl Dict {
+    /// Create a dictionary; `keys` and `values` must have the same length.
+    pub(crate) fn new(keys: Value, values: Value) -> Self {
+        Self { keys, values }
+    }
+
+    /// The keys of the dictionary.
+    pub fn keys(&self) -> &Value {
+        &self.keys
+    }
+
+    /// The values of the dictionary.
+    pub fn values(&self) -> &Value {
+        &self.values
+    }
+
+    /// Return the position of the first occurrence of `key`.
+    pub(crate) fn position(&self, key: &Value) -> Option<usize> {
+        (0..self.keys.len().unwrap_or(0)).find(|index| self.keys.item(*index).as_ref() == Some(key))
+    }
+
+    /// Return the value for `key`, or the null item of the values if the key
+    /// is missing.
+    pub(crate) fn get(&self, key: &Value) -> Value {
+        self.position(key)
+            .and_then(|index| self.values.item(index))
+            .unwrap_or_else(|| self.values.null_item())
+    }
+
+    /// Return row `index` of a table as a dictionary from column names to the
+    /// items at that row.
+    pub(crate) fn row(&self, index: usize) -> Option<Self> {
+        let items = self
+            .values
+            .items()
+            .iter()
+            .map(|column| column.item(index))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self::new(self.keys.clone(), Value::from_items(items)))
+    }
+
+    /// Apply `verb` to each column of a table, keeping the column names.
+    pub(crate) fn map_columns<E>(
+        &self,
+        verb: impl FnMut(&Value) -> Result<Value, E>,
+    ) -> Result<Self, E> {
+        let columns = self
+            .values
+            .items()
+            .iter()
+            .map(verb)
+            .collect::<Result<Vec<_>, E>>()?;
+        Ok(Self::new(self.keys.clone(), Value::List(Rc::new(columns))))
+    }
+
+    /// Merge two dictionaries by key, in order of first occurrence. Keys on
+    /// both sides are combined with `verb`; other keys keep their value.
+    fn merge(
+        &self,
+        other: &Self,
+        verb: &dyn Fn(&Value, &Value) -> Result<Value, ()>,
+    ) -> Result<Self, ()> {
+        let mut keys = self.keys.items();
+        let mut values = self.values.items();
+        for (key, value) in other.keys.items().into_iter().zip(other.values.items()) {
+            match self.position(&key) {
+                Some(index) => values[index] = verb(&values[index], &value)?,
+                None => {
+                    keys.push(key);
+                    values.push(value);
+                }
+            }
+        }
+        Ok(Self::new(
+            Value::from_items(keys),
+            Value::from_items(values),
+        ))
+    }
+}
+
+/// The shared allocation behind a vector value.
+pub(crate) struct Allocation {
+    pub(crate) address: *const (),
+    pub(crate) length: usize,
+    pub(crate) bytes: usize,
+    pub(crate) ref_count: usize,
+}
+
+/// An operand of an atomic verb: an atom or a vector of the same element type.
+enum Operand<'a, T: Clone> {
+    Atom(T),
+    Vector(Cow<'a, [T]>),
+}
+
+impl<T: Clone> Operand<'_, T> {
+    /// Convert the operand into an owned vector, enlisting an atom.
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::Atom(item) => vec![item],
+            Self::Vector(vector) => vector.into_owned(),
+        }
+    }
 }
 
 impl Value {
-    pub(crate) fn is_error(&self) -> bool {
-        matches!(self, Self::Error)
+    /// Return the number of elements, or `None` for an atom.
+    pub(crate) fn len(&self) -> Option<usize> {
+        match self {
+            Self::Atom(_)
+            | Self::Float(_)
+            | Self::Char(_)
+            | Self::Symbol(_)
+            | Self::Lambda(_)
+            | Self::Verb(_) => None,
+            Self::Vector(vector) => Some(vector.len()),
+            Self::FloatVector(vector) => Some(vector.len()),
+            Self::CharVector(vector) => Some(vector.len()),
+            Self::SymbolVector(vector) => Some(vector.len()),
+            Self::List(list) => Some(list.len()),
+            Self::Dict(dict) => dict.keys.len(),
+            Self::Table(table) => table.values.item(0).and_then(|column| column.len()),
+        }
+    }
+
+    /// Returns true if the value is an atom.
+    pub(crate) fn is_atom(&self) -> bool {
+        self.len().is_none()
     }
 
-    /// Enlist a value.
-    pub(crate) fn enlist(&self) -> Result<Self, ()> {
+    /// Return the element at `index`, or `None` if out of range.
+    pub(crate) fn item(&self, index: usize) -> Option<Self> {
         match self {
-            Self::Atom(integer) => Ok(vec![*integer].into()),
-            Self::Vector(_) => Err(()),
-            Self::Error => Ok(Self::Error),
+            Self::Atom(_)
+            | Self::Float(_)
+            | Self::Char(_)
+            | Self::Symbol(_)
+            | Self::Lambda(_)
+            | Self::Verb(_) => None,
+            Self::Vector(vector) => vector.get(index).map(Self::from),
+            Self::FloatVector(vector) => vector.get(index).map(|float| Self::Float(*float)),
+            Self::CharVector(vector) => vector.get(index).map(|char| Self::Char(*char)),
+            Self::SymbolVector(vector) => vector.get(index).cloned().map(Self::Symbol),
+            Self::List(list) => list.get(index).cloned(),
+            Self::Dict(dict) => dict.values.item(index),
+            Self::Table(table) => table.row(index).map(|row| Self::Dict(Rc::new(row))),
+        }
+    }
+
+    /// Amend a value at depth: the last index in `path` selects the items to
+    /// replace with `value`, and any before it select nested items of a list
+    /// or dictionary. Shared storage is copied before it is written.
+    pub(crate) fn amend(&mut self, path: &[Self], value: &Self) -> Result<(), KError> {
+        match path {
+            [] => {
+                *self = value.clone();
+                Ok(())
+            }
+            [index] => self.amend_indices(index, value),
+            [index, rest @ ..] => self
+                .item_mut(index)
+                .ok_or_else(KError::length)?
+                .amend(rest, value),
+        }
+    }
+
+    /// Replace the items at `indices` with `value`, or with its items if
+    /// `indices` is a vector. A dictionary adds keys it doesn't have.
+    fn amend_indices(&mut self, indices: &Self, value: &Self) -> Result<(), KError> {
+        let pairs = match indices.len() {
+            None => vec![(indices.clone(), value.clone())],
+            Some(length) if value.len().is_none_or(|count| count == length) => indices
+                .items()
+                .into_iter()
+                .enumerate()
+                .map(|(index, key)| (key, value.item(index).unwrap_or_else(|| value.clone())))
+                .collect(),
+            Some(_) => return Err(KError::length()),
+        };
+
+        if let Self::Dict(dict) = self {
+            let dict = Rc::make_mut(dict);
+            for (key, value) in pairs {
+                match dict.position(&key) {
+                    Some(position) => dict.values.amend_item(position, &value)?,
+                    None => {
+                        dict.keys = dict.keys.concatenate(&key.enlist());
+                        dict.values = dict.values.concatenate(&value.enlist());
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        let length = self.len().ok_or_else(KError::length)?;
+        for (index, value) in pairs {
+            match index.as_integer() {
+                Some(index) if (0..length as i64).contains(&index) => {
+                    self.amend_item(index as usize, &value)?
+                }
+                _ => return Err(KError::length()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the item at `index` in place. The item must fit the type of
+    /// the value: a number in a float vector, a dictionary with a value for
+    /// every column in a table, and so on. Anything else is a domain error
+    /// rather than a change of the value's type.
+    fn amend_item(&mut self, index: usize, value: &Self) -> Result<(), KError> {
+        match (&mut *self, value) {
+            (Self::Vector(vector), Self::Atom(integer)) => Rc::make_mut(vector)[index] = *integer,
+            (Self::FloatVector(vector), Self::Atom(_) | Self::Float(_)) => {
+                Rc::make_mut(vector)[index] = value.as_float().unwrap_or(f64::NAN)
+            }
+            (Self::CharVector(vector), Self::Char(char)) => Rc::make_mut(vector)[index] = *char,
+            (Self::SymbolVector(vector), Self::Symbol(symbol)) => {
+                Rc::make_mut(vector)[index] = symbol.clone()
+            }
+            (Self::List(list), _) => Rc::make_mut(list)[index] = value.clone(),
+            (Self::Table(table), Self::Dict(row)) => {
+                let mut keys = table.keys.items().into_iter();
+                let columns = table.map_columns(|column| {
+                    let key = keys.next().ok_or_else(KError::domain)?;
+                    let item = row
+                        .position(&key)
+                        .and_then(|position| row.values.item(position))
+                        .ok_or_else(KError::domain)?;
+                    let mut column = column.clone();
+                    column.amend_item(index, &item)?;
+                    Ok(column)
+                })?;
+                *table = Rc::new(columns);
+            }
+            _ => return Err(KError::domain()),
+        }
+        Ok(())
+    }
+
+    /// Return a mutable reference to an item of a list or a dictionary with
+    /// list values, copying shared storage first.
+    fn item_mut(&mut self, index: &Self) -> Option<&mut Self> {
+        match self {
+            Self::List(list) => {
+                let index = usize::try_from(index.as_integer()?).ok()?;
+                Rc::make_mut(list).get_mut(index)
+            }
+            Self::Dict(dict) => {
+                let position = dict.position(index)?;
+                Rc::make_mut(dict)
+                    .values
+                    .item_mut(&Self::Atom(position as i64))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the item that fills out-of-range indices: zero, a blank, the
+    /// empty symbol or the empty list, depending on the type.
+    pub(crate) fn null_item(&self) -> Self {
+        match self {
+            Self::Float(_) | Self::FloatVector(_) => Self::Float(0.0),
+            Self::Char(_) | Self::CharVector(_) => Self::Char(b' '),
+            Self::Symbol(_) | Self::SymbolVector(_) => Self::Symbol(Rc::from("")),
+            Self::List(_) | Self::Lambda(_) | Self::Verb(_) => Self::List(Rc::new(Vec::new())),
+            Self::Dict(dict) => dict.values.null_item(),
+            Self::Table(table) => Self::Dict(Rc::new(Dict::new(
+                table.keys.clone(),
+                Self::from_items(table.values.items().iter().map(Self::null_item).collect()),
+            ))),
+            Self::Atom(_) | Self::Vector(_) => Self::Atom(0),
+        }
+    }
+
+    /// Collect items into a value: a vector if they are all atoms of one
+    /// type, promoting integers to floats if needed, or a general list.
+    pub(crate) fn from_items(items: Vec<Self>) -> Self {
+        if items.iter().all(|item| matches!(item, Self::Atom(_))) {
+            return items
+                .iter()
+                .filter_map(Self::as_integer)
+                .collect::<Vec<_>>()
+                .into();
+        }
+        if items
+            .iter()
+            .all(|item| matches!(item, Self::Atom(_) | Self::Float(_)))
+        {
+            return items
+                .iter()
+                .filter_map(Self::as_float)
+                .collect::<Vec<_>>()
+                .into();
+        }
+        if items.iter().all(|item| matches!(item, Self::Char(_))) {
+            return Self::CharVector(Rc::new(
+                items
+                    .iter()
+                    .filter_map(|item| match item {
+                        Self::Char(char) => Some(*char),
+                        _ => None,
+                    })
+                    .collect(),
+            ));
+        }
+        if items.iter().all(|item| matches!(item, Self::Symbol(_))) {
+            return items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Self::Symbol(symbol) => Some(symbol),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .into();
+        }
+        Self::List(Rc::new(items))
+    }
+
+    /// Return the integer in an integer atom.
+    pub(crate) fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Atom(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// Return the number in a numeric atom as a float.
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Atom(integer) => Some(*integer as f64),
+            Self::Float(float) => Some(*float),
+            _ => None,
+        }
+    }
+
+    /// Return the shared allocation behind a vector, or `None` for an atom.
+    pub(crate) fn allocation(&self) -> Option<Allocation> {
+        match self {
+            Self::List(list) => Some(Allocation {
+                address: Rc::as_ptr(list).cast(),
+                length: list.len(),
+                bytes: list.len() * std::mem::size_of::<Value>(),
+                ref_count: Rc::strong_count(list),
+            }),
+            Self::Atom(_)
+            | Self::Float(_)
+            | Self::Char(_)
+            | Self::Symbol(_)
+            | Self::Lambda(_)
+            | Self::Verb(_) => None,
+            Self::Vector(vector) => Some(Allocation {
+                address: Rc::as_ptr(vector).cast(),
+                length: vector.len(),
+                bytes: vector.len() * std::mem::size_of::<i64>(),
+                ref_count: Rc::strong_count(vector),
+            }),
+            Self::FloatVector(vector) => Some(Allocation {
+                address: Rc::as_ptr(vector).cast(),
+                length: vector.len(),
+                bytes: vector.len() * std::mem::size_of::<f64>(),
+                ref_count: Rc::strong_count(vector),
+            }),
+            Self::CharVector(vector) => Some(Allocation {
+                address: Rc::as_ptr(vector).cast(),
+                length: vector.len(),
+                bytes: vector.len(),
+                ref_count: Rc::strong_count(vector),
+            }),
+            Self::SymbolVector(vector) => Some(Allocation {
+                address: Rc::as_ptr(vector).cast(),
+                length: vector.len(),
+                bytes: vector.len() * std::mem::size_of::<Rc<str>>(),
+                ref_count: Rc::strong_count(vector),
+            }),
+            Self::Dict(dict) | Self::Table(dict) => Some(Allocation {
+                address: Rc::as_ptr(dict).cast(),
+                length: self.len().unwrap_or(0),
+                bytes: std::mem::size_of::<Dict>(),
+                ref_count: Rc::strong_count(dict),
+            }),
+        }
+    }
+
+    /// Return the allocations behind a value and everything nested in it.
+    pub(crate) fn nested_allocations(&self) -> Vec<Allocation> {
+        let mut allocations: Vec<Allocation> = self.allocation().into_iter().collect();
+        match self {
+            Self::List(list) => {
+                for item in list.iter() {
+                    allocations.extend(item.nested_allocations());
+                }
+            }
+            Self::Dict(dict) | Self::Table(dict) => {
+                allocations.extend(dict.keys.nested_allocations());
+                allocations.extend(dict.values.nested_allocations());
+            }
+            _ => {}
+        }
+        allocations
+    }
+
+    /// Enlist a value: an atom becomes a one-element vector, anything else a
+    /// one-item list.
+    pub(crate) fn enlist(&self) -> Self {
+        match self {
+            Self::Atom(integer) => vec![*integer].into(),
+            Self::Float(float) => vec![*float].into(),
+            Self::Char(char) => Self::CharVector(Rc::new(vec![*char])),
+            Self::Symbol(symbol) => vec![symbol.clone()].into(),
+            _ => Self::List(Rc::new(vec![self.clone()])),
         }
     }
 
     /// Reverse a value.
     pub(crate) fn reverse(&self) -> Result<Self, ()> {
         match self {
-            Self::Atom(_) => Err(()),
+            Self::Atom(_)
+            | Self::Float(_)
+            | Self::Char(_)
+            | Self::Symbol(_)
+            | Self::Lambda(_)
+            | Self::Verb(_) => Err(()),
             Self::Vector(vector) => Ok(vector.iter().rev().cloned().collect::<Vec<_>>().into()),
-            Self::Error => Ok(Self::Error),
+            Self::FloatVector(vector) => {
+                Ok(vector.iter().rev().cloned().collect::<Vec<_>>().into())
+            }
+            Self::CharVector(vector) => Ok(Self::CharVector(Rc::new(
+                vector.iter().rev().cloned().collect(),
+            ))),
+            Self::SymbolVector(vector) => {
+                Ok(vector.iter().rev().cloned().collect::<Vec<_>>().into())
+            }
+            Self::List(list) => Ok(Self::List(Rc::new(list.iter().rev().cloned().collect()))),
+            Self::Dict(dict) => Ok(Self::Dict(Rc::new(Dict::new(
+                dict.keys.reverse()?,
+                dict.values.reverse()?,
+            )))),
+            Self::Table(table) => Ok(Self::Table(Rc::new(table.map_columns(Self::reverse)?))),
+        }
+    }
+
+    /// Negate a numeric value.
+    pub(crate) fn negate(&self) -> Result<Self, ()> {
+        match self {
+            Self::Atom(integer) => Ok(Self::Atom(integer.wrapping_neg())),
+            Self::Float(float) => Ok(Self::Float(-float)),
+            Self::Vector(vector) => Ok(vector
+                .iter()
+                .map(|integer| integer.wrapping_neg())
+                .collect::<Vec<_>>()
+                .into()),
+            Self::FloatVector(vector) => {
+                Ok(vector.iter().map(|float| -float).collect::<Vec<_>>().into())
+            }
+            Self::List(list) => Ok(Self::from_items(
+                list.iter()
+                    .map(Self::negate)
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            Self::Dict(dict) => Ok(Self::Dict(Rc::new(Dict::new(
+                dict.keys.clone(),
+                dict.values.negate()?,
+            )))),
+            Self::Char(_)
+            | Self::Symbol(_)
+            | Self::CharVector(_)
+            | Self::SymbolVector(_)
+            | Self::Table(_)
+            | Self::Lambda(_)
+            | Self::Verb(_) => Err(()),
+        }
+    }
+
+    /// Concatenate two values, promoting to floats if either side is a float.
+    /// Values of different types concatenate into a general list. Joining
+    /// two dictionaries updates the left one with the entries of the right,
+    /// and tables with the same columns join their rows.
+    pub(crate) fn concatenate(&self, other: &Self) -> Self {
+        if let (Self::Dict(left), Self::Dict(right)) = (self, other)
+            && let Ok(dict) = left.merge(right, &|_, value| Ok(value.clone()))
+        {
+            return Self::Dict(Rc::new(dict));
+        }
+        if let (Self::Table(left), Self::Table(right)) = (self, other)
+            && left.keys == right.keys
+        {
+            let columns = left
+                .values
+                .items()
+                .iter()
+                .zip(right.values.items())
+                .map(|(left, right)| left.concatenate(&right))
+                .collect();
+            return Self::Table(Rc::new(Dict::new(
+                left.keys.clone(),
+                Self::List(Rc::new(columns)),
+            )));
+        }
+        if let (Some(left), Some(right)) = (self.symbols(), other.symbols()) {
+            let mut vector = left.into_vec();
+            vector.extend(right.into_vec());
+            return vector.into();
+        }
+        if let (Some(left), Some(right)) = (self.characters(), other.characters()) {
+            let mut vector = left.into_vec();
+            vector.extend(right.into_vec());
+            return Self::CharVector(Rc::new(vector));
+        }
+        if let (Some(left), Some(right)) = (self.integers(), other.integers()) {
+            let mut vector = left.into_vec();
+            vector.extend(right.into_vec());
+            return vector.into();
         }
+        if let (Some(left), Some(right)) = (self.floats(), other.floats()) {
+            let mut vector = left.into_vec();
+            vector.extend(right.into_vec());
+            return vector.into();
+        }
+
+        let mut items = self.items();
+        items.extend(other.items());
+        Self::from_items(items)
     }
 
-    /// Apply a dyadic verb to a value.
-    pub(crate) fn apply_dyadic_verb(
+    /// Return the items of a value, treating an atom as a single item.
+    pub(crate) fn items(&self) -> Vec<Self> {
+        match self.len() {
+            Some(length) => (0..length).filter_map(|index| self.item(index)).collect(),
+            None => vec![self.clone()],
+        }
+    }
+
+    /// Apply `verb` to corresponding items of two values, extending an atom
+    /// against the items of the other side.
+    fn each_pair(
         &self,
         other: &Self,
-        verb: fn(i64, i64) -> i64,
+        verb: &dyn Fn(&Self, &Self) -> Result<Self, ()>,
     ) -> Result<Self, ()> {
-        match (self, other) {
-            (Self::Atom(a), Self::Atom(b)) => Ok(verb(*a, *b).into()),
-            (Self::Vector(a), Self::Atom(b)) => {
-                Ok(a.iter().map(|x| verb(*x, *b)).collect::<Vec<_>>().into())
-            }
-            (Self::Atom(_), Self::Vector(_)) => other.apply_dyadic_verb(self, verb),
-            (Self::Vector(a), Self::Vector(b)) => {
-                if a.len() != b.len() {
-                    return Err(());
-                }
-                Ok(a.iter()
-                    .zip(b.iter())
-                    .map(|(x, y)| verb(*x, *y))
-                    .collect::<Vec<_>>()
-                    .into())
+        let length = match (self.len(), other.len()) {
+            (Some(left), Some(right)) if left != right => return Err(()),
+            (Some(length), _) | (None, Some(length)) => length,
+            (None, None) => return verb(self, other),
+        };
+
+        let items = (0..length)
+            .map(|index| {
+                let left = self.item(index).unwrap_or_else(|| self.clone());
+                let right = other.item(index).unwrap_or_else(|| other.clone());
+                verb(&left, &right)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_items(items))
+    }
+
+    /// Apply `verb` to the values of a dictionary. Two dictionaries are
+    /// merged by key, applying `verb` where the keys match.
+    fn each_value(
+        &self,
+        other: &Self,
+        verb: &dyn Fn(&Self, &Self) -> Result<Self, ()>,
+    ) -> Result<Self, ()> {
+        let dict = match (self, other) {
+            (Self::Dict(left), Self::Dict(right)) => left.merge(right, verb)?,
+            (Self::Dict(dict), _) => Dict::new(dict.keys.clone(), verb(&dict.values, other)?),
+            (_, Self::Dict(dict)) => Dict::new(dict.keys.clone(), verb(self, &dict.values)?),
+            _ => return verb(self, other),
+        };
+        Ok(Self::Dict(Rc::new(dict)))
+    }
+
+    /// View a value as a character operand.
+    fn characters(&self) -> Option<Operand<'_, u8>> {
+        match self {
+            Self::Char(char) => Some(Operand::Atom(*char)),
+            Self::CharVector(vector) => Some(Operand::Vector(Cow::Borrowed(vector))),
+            _ => None,
+        }
+    }
+
+    /// View a value as a symbol operand.
+    fn symbols(&self) -> Option<Operand<'_, Rc<str>>> {
+        match self {
+            Self::Symbol(symbol) => Some(Operand::Atom(symbol.clone())),
+            Self::SymbolVector(vector) => Some(Operand::Vector(Cow::Borrowed(vector))),
+            _ => None,
+        }
+    }
+
+    /// View a value as an integer operand.
+    fn integers(&self) -> Option<Operand<'_, i64>> {
+        match self {
+            Self::Atom(integer) => Some(Operand::Atom(*integer)),
+            Self::Vector(vector) => Some(Operand::Vector(Cow::Borrowed(vector))),
+            _ => None,
+        }
+    }
+
+    /// View a value as a float operand, promoting integers.
+    fn floats(&self) -> Option<Operand<'_, f64>> {
+        match self {
+            Self::Atom(integer) => Some(Operand::Atom(*integer as f64)),
+            Self::Float(float) => Some(Operand::Atom(*float)),
+            Self::Vector(vector) => Some(Operand::Vector(Cow::Owned(
+                vector.iter().map(|integer| *integer as f64).collect(),
+            ))),
+            Self::FloatVector(vector) => Some(Operand::Vector(Cow::Borrowed(vector))),
+            _ => None,
+        }
+    }
+
+    /// Apply a dyadic verb to a value. Integers are promoted to floats when
+    /// either side is a float, and the verb penetrates general lists and
+    /// applies to the values of dictionaries.
+    pub(crate) fn apply_dyadic_verb<I, F>(
+        &self,
+        other: &Self,
+        integer_verb: impl Fn(i64, i64) -> I,
+        float_verb: impl Fn(f64, f64) -> F,
+    ) -> Result<Self, ()>
+    where
+        Self: From<I> + From<Vec<I>> + From<F> + From<Vec<F>>,
+    {
+        self.apply_atomic(other, &integer_verb, &float_verb)
+    }
+
+    fn apply_atomic<I, F>(
+        &self,
+        other: &Self,
+        integer_verb: &dyn Fn(i64, i64) -> I,
+        float_verb: &dyn Fn(f64, f64) -> F,
+    ) -> Result<Self, ()>
+    where
+        Self: From<I> + From<Vec<I>> + From<F> + From<Vec<F>>,
+    {
+        if matches!(self, Self::Dict(_)) || matches!(other, Self::Dict(_)) {
+            return self.each_value(other, &|left, right| {
+                left.apply_atomic(right, integer_verb, float_verb)
+            });
+        }
+        if matches!(self, Self::List(_)) || matches!(other, Self::List(_)) {
+            return self.each_pair(other, &|left, right| {
+                left.apply_atomic(right, integer_verb, float_verb)
+            });
+        }
+        if let (Some(left), Some(right)) = (self.integers(), other.integers()) {
+            return zip_with(left, right, |a, b| integer_verb(*a, *b));
+        }
+        match (self.floats(), other.floats()) {
+            (Some(left), Some(right)) => zip_with(left, right, |a, b| float_verb(*a, *b)),
+            _ => Err(()),
+        }
+    }
+
+    /// Compare two values element-wise, returning 1 where `test` accepts the
+    /// ordering and 0 otherwise. Characters compare with characters, symbols
+    /// with symbols and numbers with numbers.
+    pub(crate) fn apply_comparison(
+        &self,
+        other: &Self,
+        test: impl Fn(Ordering) -> bool,
+    ) -> Result<Self, ()> {
+        self.compare(other, &test)
+    }
+
+    fn compare(&self, other: &Self, test: &dyn Fn(Ordering) -> bool) -> Result<Self, ()> {
+        if matches!(self, Self::Dict(_)) || matches!(other, Self::Dict(_)) {
+            return self.each_value(other, &|left, right| left.compare(right, test));
+        }
+        if matches!(self, Self::List(_)) || matches!(other, Self::List(_)) {
+            return self.each_pair(other, &|left, right| left.compare(right, test));
+        }
+        if let (Some(left), Some(right)) = (self.characters(), other.characters()) {
+            return zip_with(left, right, |a, b| test(a.cmp(b)) as i64);
+        }
+        if let (Some(left), Some(right)) = (self.symbols(), other.symbols()) {
+            return zip_with(left, right, |a, b| test(a.cmp(b)) as i64);
+        }
+        self.apply_dyadic_verb(
+            other,
+            |a, b| test(a.cmp(&b)) as i64,
+            |a, b| a.partial_cmp(&b).is_some_and(test) as i64,
+        )
+    }
+}
+
+/// Combine two operands element-wise, extending an atom against a vector.
+fn zip_with<T: Clone, R>(
+    left: Operand<'_, T>,
+    right: Operand<'_, T>,
+    verb: impl Fn(&T, &T) -> R,
+) -> Result<Value, ()>
+where
+    Value: From<R> + From<Vec<R>>,
+{
+    match (left, right) {
+        (Operand::Atom(a), Operand::Atom(b)) => Ok(verb(&a, &b).into()),
+        (Operand::Vector(a), Operand::Atom(b)) => {
+            Ok(a.iter().map(|x| verb(x, &b)).collect::<Vec<_>>().into())
+        }
+        (Operand::Atom(a), Operand::Vector(b)) => {
+            Ok(b.iter().map(|y| verb(&a, y)).collect::<Vec<_>>().into())
+        }
+        (Operand::Vector(a), Operand::Vector(b)) => {
+            if a.len() != b.len() {
+                return Err(());
             }
-            _ => Ok(Self::Error),
+            Ok(a.iter()
+                .zip(b.iter())
+                .map(|(x, y)| verb(x, y))
+                .collect::<Vec<_>>()
+                .into())
         }
     }
 }
 
+/// Format a float so that it reads back as a float.
+fn format_float(float: f64) -> String {
+    if float.is_nan() {
+        "0n".to_string()
+    } else if float.is_infinite() {
+        if float > 0.0 { "0w" } else { "-0w" }.to_string()
+    } else if float.fract() == 0.0 && float.abs() < 1e16 {
+        format!("{:.1}", float)
+    } else {
+        format!("{}", float)
+    }
+}
+
+/// Escape characters so that they read back inside a string literal.
+fn escape(chars: &[u8]) -> String {
+    String::from_utf8_lossy(chars)
+        .chars()
+        .map(|char| match char {
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            _ => char.to_string(),
+        })
+        .collect()
+}
+
+/// Write the elements of a vector so that they read back as the same vector.
+fn write_vector<T>(
+    f: &mut std::fmt::Formatter<'_>,
+    vector: &[T],
+    empty: &str,
+    format: impl Fn(&T) -> String,
+) -> std::fmt::Result {
+    match vector {
+        [] => write!(f, "{}", empty),
+        [item] => write!(f, ",{}", format(item)),
+        [first, rest @ ..] => {
+            write!(f, "{}", format(first))?;
+            for item in rest {
+                write!(f, " {}", format(item))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Format a table cell: symbols and characters appear bare, other values as
+/// they display.
+fn format_cell(value: &Value) -> String {
+    match value {
+        Value::Symbol(symbol) => symbol.to_string(),
+        Value::Char(char) => escape(&[*char]),
+        _ => value.to_string(),
+    }
+}
+
+/// Write a table as aligned columns under a header of column names.
+fn write_table(f: &mut std::fmt::Formatter<'_>, table: &Dict) -> std::fmt::Result {
+    let columns = table
+        .keys
+        .items()
+        .iter()
+        .zip(table.values.items())
+        .map(|(name, column)| {
+            let mut cells = vec![format_cell(name)];
+            cells.extend(column.items().iter().map(format_cell));
+            cells
+        })
+        .collect::<Vec<_>>();
+    let widths = columns
+        .iter()
+        .map(|cells| cells.iter().map(String::len).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let line = |row: usize| {
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(cells, width)| format!("{:width$}", cells[row]))
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end()
+            .to_string()
+    };
+    let rows = columns.first().map_or(0, Vec::len);
+
+    write!(f, "{}", line(0))?;
+    let total = widths.iter().sum::<usize>() + widths.len().saturating_sub(1);
+    write!(f, "\n{}", "-".repeat(total))?;
+    for row in 1..rows {
+        write!(f, "\n{}", line(row))?;
+    }
+    Ok(())
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Atom(integer) => write!(f, "{}", integer),
-            Value::Vector(vector) => {
-                for integer in vector.iter() {
-                    write!(f, "{} ", integer)?;
-                }
-                Ok(())
+            Value::Float(float) => write!(f, "{}", format_float(*float)),
+            Value::Char(char) => write!(f, "\"{}\"", escape(&[*char])),
+            Value::Symbol(symbol) => write!(f, "`{}", symbol),
+            Value::Vector(vector) => write_vector(f, vector, "!0", |integer| integer.to_string()),
+            Value::FloatVector(vector) => {
+                write_vector(f, vector, "0#0.0", |float| format_float(*float))
             }
-            Value::Error => write!(f, "Error"),
+            Value::CharVector(vector) => match vector.as_slice() {
+                [char] => write!(f, ",\"{}\"", escape(&[*char])),
+                chars => write!(f, "\"{}\"", escape(chars)),
+            },
+            Value::SymbolVector(vector) => match vector.as_slice() {
+                [] => write!(f, "0#`"),
+                [symbol] => write!(f, ",`{}", symbol),
+                symbols => symbols
+                    .iter()
+                    .try_for_each(|symbol| write!(f, "`{}", symbol)),
+            },
+            Value::List(list) => match list.as_slice() {
+                [item] => write!(f, ",{}", item),
+                items => {
+                    write!(f, "(")?;
+                    for (index, item) in items.iter().enumerate() {
+                        if index > 0 {
+                            write!(f, ";")?;
+                        }
+                        write!(f, "{}", item)?;
+                    }
+                    write!(f, ")")
+                }
+            },
+            // Keys that are not a plain run of items need parentheses to read
+            // back, e.g. `(,`a)!,1`.
+            Value::Dict(dict) => match dict.keys.len() {
+                Some(2..) => write!(f, "{}!{}", dict.keys, dict.values),
+                _ => write!(f, "({})!{}", dict.keys, dict.values),
+            },
+            Value::Table(table) => write_table(f, table),
+            Value::Lambda(lambda) => write!(f, "{}", lambda.source),
+            Value::Verb(verb) => write!(f, "{}", verb),
         }
     }
 }
 
-impl Neg for Value {
-    type Output = Value;
-
-    fn neg(self) -> Self::Output {
+/// Display a verb the way it is written.
+impl Display for Verb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Value::Atom(integer) => Value::Atom(integer.wrapping_neg()),
-            Value::Vector(vector) => vector
-                .iter()
-                .map(|integer| integer.wrapping_neg())
-                .collect::<Vec<_>>()
-                .into(),
-            Value::Error => Value::Error,
+            Verb::Builtin(verb) | Verb::Monadic(verb) => write!(f, "{}", verb_token(*verb)),
+            Verb::Lambda(lambda) => write!(f, "{}", lambda.source),
+            Verb::Derived(verb, adverb) => write!(f, "{}{}", verb, adverb_token(*adverb)),
+            Verb::Projection(verb, arguments) => {
+                write!(f, "{}[", verb)?;
+                for (index, argument) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, "]")
+            }
+            Verb::Composition(outer, inner) => write!(f, "{}{}", outer, inner),
         }
     }
 }
@@ -112,8 +1020,38 @@ macro_rules! impl_from_integer {
 
 impl_from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, usize);
 
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
 impl From<Vec<i64>> for Value {
     fn from(value: Vec<i64>) -> Self {
         Value::Vector(Rc::new(value))
     }
 }
+
+impl From<Vec<f64>> for Value {
+    fn from(value: Vec<f64>) -> Self {
+        Value::FloatVector(Rc::new(value))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::CharVector(Rc::new(value.as_bytes().to_vec()))
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::CharVector(Rc::new(value.into_bytes()))
+    }
+}
+
+impl From<Vec<Rc<str>>> for Value {
+    fn from(value: Vec<Rc<str>>) -> Self {
+        Value::SymbolVector(Rc::new(value))
+    }
+}
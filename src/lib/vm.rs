@@ -0,0 +1,122 @@
+use crate::compile::Op;
+use crate::error::KError;
+use crate::eval::truth;
+use crate::runtime::{Runtime, apply_adverb, apply_dyadic_verb, apply_monadic_verb, call, verb_of};
+use crate::value::{Value, Verb};
+use std::rc::Rc;
+
+/// Run compiled code on a value stack and return the value it leaves on top.
+pub(crate) fn execute(runtime: &mut Runtime, code: &[Op]) -> Result<Value, KError> {
+    let mut stack = Vec::new();
+    let mut counter = 0;
+
+    while let Some(op) = code.get(counter) {
+        counter += 1;
+        match op {
+            Op::Noun(token, span) => stack.push(
+                runtime
+                    .noun_from_token(token)
+                    .map_err(|error| error.with_span(*span))?,
+            ),
+            Op::List(0) => stack.push(Value::List(Rc::new(Vec::new()))),
+            Op::List(count) => {
+                let items = pop_items(&mut stack, *count);
+                stack.push(Value::from_items(items));
+            }
+            Op::Monad(verb, span) => {
+                let value = pop(&mut stack);
+                let result = apply_monadic_verb(runtime, *verb, value);
+                stack.push(result.map_err(|error| error.with_span(*span))?);
+            }
+            Op::Dyad(verb, span) => {
+                let left = pop(&mut stack);
+                let right = pop(&mut stack);
+                let result = apply_dyadic_verb(runtime, *verb, left, right);
+                stack.push(result.map_err(|error| error.with_span(*span))?);
+            }
+            Op::Adverb {
+                adverb,
+                verb,
+                dyadic,
+                span,
+            } => {
+                let left = dyadic.then(|| pop(&mut stack));
+                let verb = match verb {
+                    Some(verb) => Verb::Builtin(*verb),
+                    None => verb_of(pop(&mut stack)).map_err(|error| error.with_span(*span))?,
+                };
+                let right = pop(&mut stack);
+                let result = apply_adverb(runtime, *adverb, &verb, left, right);
+                stack.push(result.map_err(|error| error.with_span(*span))?);
+            }
+            Op::Assign(name) => {
+                let value = pop(&mut stack);
+                stack.push(runtime.assign(name.clone(), value));
+            }
+            Op::AssignGlobal(name) => {
+                let value = pop(&mut stack);
+                stack.push(runtime.assign_global(name.clone(), value));
+            }
+            Op::Amend(name, count, span) => {
+                let path = pop_items(&mut stack, *count);
+                let value = pop(&mut stack);
+                let result = runtime.amend(name, &path, value);
+                stack.push(result.map_err(|error| error.with_span(*span))?);
+            }
+            Op::Apply(count, span) => {
+                let function = pop(&mut stack);
+                let arguments = pop_items(&mut stack, *count);
+                let result = call(runtime, function, arguments);
+                stack.push(result.map_err(|error| error.with_span(*span))?);
+            }
+            Op::Pop => {
+                stack.pop();
+            }
+            Op::Jump(target) => counter = *target,
+            Op::JumpUnless(target, span) => {
+                if !truth(pop(&mut stack)).map_err(|error| error.with_span(*span))? {
+                    counter = *target;
+                }
+            }
+            Op::Repeat(target, span) => match stack.last_mut() {
+                Some(Value::Atom(count)) if *count > 0 => *count -= 1,
+                Some(Value::Atom(_)) => {
+                    stack.pop();
+                    counter = *target;
+                }
+                _ => return Err(KError::domain().with_span(*span)),
+            },
+            Op::Verb(verb) => stack.push(Value::Verb(Rc::new(verb.clone()))),
+            Op::Derive(adverb) => {
+                let verb = verb_of(pop(&mut stack))?;
+                stack.push(Value::Verb(Rc::new(Verb::Derived(Rc::new(verb), *adverb))));
+            }
+            Op::Project(verb) => {
+                let left = pop(&mut stack);
+                let verb = Verb::Projection(Rc::new(Verb::Builtin(*verb)), vec![left]);
+                stack.push(Value::Verb(Rc::new(verb)));
+            }
+            Op::Compose => {
+                let outer = verb_of(pop(&mut stack))?;
+                let inner = verb_of(pop(&mut stack))?;
+                let verb = Verb::Composition(Rc::new(outer), Rc::new(inner));
+                stack.push(Value::Verb(Rc::new(verb)));
+            }
+            Op::Error(error) => return Err(error.clone()),
+        }
+    }
+
+    Ok(pop(&mut stack))
+}
+
+/// Pop a value the compiler has arranged to be on the stack.
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("compiled code keeps the stack balanced")
+}
+
+/// Pop `count` values, the first of which is on top of the stack.
+fn pop_items(stack: &mut Vec<Value>, count: usize) -> Vec<Value> {
+    let mut items = stack.split_off(stack.len() - count);
+    items.reverse();
+    items
+}
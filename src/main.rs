@@ -1,8 +1,26 @@
 use ksimple::{Runtime, run_batch, run_repl};
 const BANNER: &str = "k/simple in Rust";
 
+/// The stack size of the interpreter thread, large enough for lambda calls
+/// to reach `MAX_DEPTH` even in a debug build.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// How deeply lambda calls may nest on the interpreter thread.
+const MAX_DEPTH: usize = 1000;
+
 fn main() {
+    let interpreter = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to start the interpreter thread");
+    if interpreter.join().is_err() {
+        std::process::exit(1);
+    }
+}
+
+fn run() {
     let mut runtime = Runtime::new();
+    runtime.set_max_depth(MAX_DEPTH);
     let args: Vec<String> = std::env::args().collect();
 
     match args.as_slice() {
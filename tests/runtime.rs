@@ -0,0 +1,119 @@
+use ksimple::{Runtime, Span, Value, Verb};
+use std::rc::Rc;
+
+#[test]
+fn eval_returns_the_last_statement() {
+    let mut runtime = Runtime::new();
+    assert_eq!(runtime.eval("a:2;a*3").unwrap(), Value::Atom(6));
+    assert_eq!(runtime.eval("1.5*2").unwrap(), Value::Float(3.0));
+    assert_eq!(runtime.eval("!3").unwrap(), vec![0, 1, 2].into());
+    assert_eq!(runtime.eval("\"abc\"").unwrap(), "abc".into());
+}
+
+#[test]
+fn eval_errors_carry_kind_and_origin() {
+    let mut runtime = Runtime::new();
+
+    let error = runtime.eval("1+`a").unwrap_err();
+    assert_eq!(error.kind(), "domain");
+    assert_eq!(error.origin().verb, Some("+"));
+    assert_eq!(error.origin().span, Some(Span::new(1, 2)));
+    assert_eq!(error.to_string(), "+ domain");
+
+    let error = runtime.eval("`a`b!1 2 3").unwrap_err();
+    assert_eq!(error.kind(), "length");
+    assert_eq!(error.origin().verb, Some("!"));
+
+    let error = runtime.eval("1+undefined").unwrap_err();
+    assert_eq!(error.kind(), "value");
+    assert_eq!(error.origin().span, Some(Span::new(2, 11)));
+
+    let error = runtime.eval("1+(2").unwrap_err();
+    assert_eq!(error.kind(), "parse");
+}
+
+#[test]
+fn globals_round_trip() {
+    let mut runtime = Runtime::new();
+    assert_eq!(runtime.global("x"), None);
+
+    runtime.set_global("x", vec![1, 2, 3]).unwrap();
+    assert_eq!(runtime.global("x"), Some(vec![1, 2, 3].into()));
+    assert_eq!(runtime.eval("x,4").unwrap(), vec![1, 2, 3, 4].into());
+
+    runtime.eval("y:10").unwrap();
+    assert_eq!(runtime.global("y"), Some(Value::Atom(10)));
+
+    runtime.set_global("s", "hi").unwrap();
+    assert_eq!(runtime.eval("#s").unwrap(), Value::Atom(2));
+}
+
+#[test]
+fn set_global_rejects_invalid_names() {
+    let mut runtime = Runtime::new();
+    for name in ["", "1x", "a-b", "x y", "_"] {
+        let error = runtime.set_global(name, 1).unwrap_err();
+        assert_eq!(error.kind(), "domain", "{name:?}");
+        assert_eq!(runtime.global(name), None);
+    }
+    runtime.set_global("x1", 1).unwrap();
+}
+
+#[test]
+fn workspace_bytes_counts_shared_vectors_once() {
+    let mut runtime = Runtime::new();
+    assert_eq!(runtime.workspace_bytes(), 0);
+
+    runtime.eval("a:!10").unwrap();
+    assert_eq!(runtime.workspace_bytes(), 80);
+
+    runtime.eval("b:a").unwrap();
+    assert_eq!(runtime.workspace_bytes(), 80);
+
+    runtime.eval("c:1.0 2.0").unwrap();
+    assert_eq!(runtime.workspace_bytes(), 96);
+
+    runtime.eval("d:(a;c)").unwrap();
+    let list = 2 * std::mem::size_of::<Value>();
+    assert_eq!(runtime.workspace_bytes(), 96 + list);
+
+    runtime.eval("a:0;b:0;c:0;d:0").unwrap();
+    assert_eq!(runtime.workspace_bytes(), 0);
+}
+
+#[test]
+fn deep_recursion_is_a_stack_error_on_a_default_thread() {
+    for bytecode in [true, false] {
+        let mut runtime = Runtime::new();
+        runtime.set_bytecode(bytecode);
+        runtime.eval("r:{$[x=0;0;1+r[x-1]]}").unwrap();
+
+        let depth = runtime.max_depth() as i64;
+        assert_eq!(
+            runtime.eval(&format!("r {}", depth - 1)).unwrap(),
+            Value::Atom(depth - 1)
+        );
+        assert_eq!(
+            runtime.eval(&format!("r {depth}")).unwrap_err().kind(),
+            "stack"
+        );
+
+        runtime.set_max_depth(10);
+        assert_eq!(runtime.eval("r 10").unwrap_err().kind(), "stack");
+        assert_eq!(runtime.eval("r 9").unwrap(), Value::Atom(9));
+    }
+}
+
+#[test]
+fn verb_values_display_without_panicking() {
+    let mut runtime = Runtime::new();
+    assert_eq!(runtime.eval("+/").unwrap().to_string(), "+/");
+    assert_eq!(runtime.eval("*|").unwrap().to_string(), "*|");
+
+    let verb = Verb::Derived(Rc::new(Verb::Builtin(40)), 40);
+    assert_eq!(verb.to_string(), "  ");
+    runtime
+        .set_global("v", Value::Verb(Rc::new(Verb::Builtin(40))))
+        .unwrap();
+    assert_eq!(runtime.eval("1 v 2").unwrap_err().kind(), "rank");
+}